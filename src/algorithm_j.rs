@@ -3,74 +3,405 @@ use std::collections::HashMap;
 use crate::{
     ctxt::Ctxt,
     expr::Expr,
-    types::{Mono, Poly, TypeVar},
+    typed_expr::TypedExpr,
+    types::{Mono, Poly, Subst, TypeVar, open_recursive},
 };
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InferenceError {
-    UnknownVar(String),
-    ImpossibleUnification(Mono, Mono),
+    /// The variable is not bound in the context. The candidates are bound variables with similar names, closest first.
+    /// If the variable is bound elsewhere in the expression, it is out of scope at this occurrence.
+    UnknownVar {
+        name: String,
+        candidates: Vec<String>,
+        bound_elsewhere: bool,
+    },
+    /// The expected type, e.g., the type of an applied function, cannot be unified with the type found, e.g., the type
+    /// of the argument. The path leads to the clashing subterms, by the indices of the type arguments along the way.
+    ImpossibleUnification {
+        expected: Mono,
+        found: Mono,
+        path: Box<[usize]>,
+    },
     RecursiveType(Mono, String),
 }
 
 pub type InferenceResult<T> = Result<T, InferenceError>;
 
+impl std::fmt::Display for InferenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InferenceError::UnknownVar {
+                name,
+                candidates,
+                bound_elsewhere,
+            } => {
+                write!(f, "Encountered unknown variable during inference: '{name}'")?;
+                if *bound_elsewhere {
+                    write!(f, ", which is bound elsewhere in the expression but not in scope here")?;
+                }
+                if !candidates.is_empty() {
+                    let candidates: Vec<String> = candidates.iter().map(|y| format!("'{y}'")).collect();
+                    write!(f, "; similar names in scope: {}", candidates.join(", "))?;
+                }
+                Ok(())
+            }
+            InferenceError::ImpossibleUnification { expected, found, path } => {
+                write!(f, "Expected type '{expected}', found '{found}'")?;
+                if !path.is_empty() {
+                    let (tau1, tau2) = (expected.at(path), found.at(path));
+                    write!(f, ", but '{tau1}' and '{tau2}' cannot be unified")?;
+                }
+                Ok(())
+            }
+            InferenceError::RecursiveType(tau, alpha) => {
+                write!(f, "Unifying '{tau}' and '{alpha}' would create recursive type")
+            }
+        }
+    }
+}
+
+/// An inference error together with the sub-expression that was being processed when it was detected.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LocatedError<'a> {
+    pub error: InferenceError,
+    pub expr: &'a Expr,
+}
+
+impl InferenceError {
+    pub fn at(self, expr: &Expr) -> LocatedError<'_> {
+        LocatedError { error: self, expr }
+    }
+
+    /// A stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            InferenceError::UnknownVar { .. } => "E0001",
+            InferenceError::ImpossibleUnification { .. } => "E0002",
+            InferenceError::RecursiveType(..) => "E0003",
+        }
+    }
+
+    /// The error for a variable that is not bound in the context, with the similarly named variables of the context as
+    /// candidates.
+    #[allow(nonstandard_style)]
+    pub fn unknown_var(x: &str, Gamma: &Ctxt) -> InferenceError {
+        InferenceError::UnknownVar {
+            name: x.into(),
+            candidates: Gamma.similar(x),
+            bound_elsewhere: false,
+        }
+    }
+
+    /// Notes whether an unknown variable is bound elsewhere in the expression `e` it occurs in. Since every binding
+    /// that encloses the occurrence extends the context, such a binding must be in another branch of `e`.
+    pub(crate) fn in_expr(self, e: &Expr) -> InferenceError {
+        match self {
+            InferenceError::UnknownVar { name, candidates, .. } => InferenceError::UnknownVar {
+                bound_elsewhere: e.bound().contains(&name),
+                name,
+                candidates,
+            },
+            err => err,
+        }
+    }
+
+    /// The error for two types with different outermost type constructors.
+    pub fn mismatch(expected: Mono, found: Mono) -> InferenceError {
+        InferenceError::ImpossibleUnification {
+            expected,
+            found,
+            path: Box::new([]),
+        }
+    }
+
+    /// Places a clash between the `i`-th type arguments of the given types into these types.
+    pub(crate) fn within(self, i: usize, expected: &Mono, found: &Mono) -> InferenceError {
+        match self {
+            InferenceError::ImpossibleUnification { path, .. } => {
+                let path = std::iter::once(i).chain(path).collect();
+                InferenceError::ImpossibleUnification {
+                    expected: expected.clone(),
+                    found: found.clone(),
+                    path,
+                }
+            }
+            err => err,
+        }
+    }
+}
+
+/// A step taken by Algorithm J, recorded when tracing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    NewVar(TypeVar),
+    /// A unification, with both types canonicalized.
+    Unify(Mono, Mono),
+    Alias(TypeVar, Mono),
+    Instantiate(Poly, Mono),
+    Generalize(Poly),
+}
+
+/// The events recorded during inference, each tagged with the sub-expression being processed.
+#[derive(Clone, Debug, Default)]
+pub struct Trace<'a> {
+    pub events: Vec<(Event, &'a Expr)>,
+}
+
 #[allow(nonstandard_style)]
 pub fn infer(e: &Expr, Gamma: &Ctxt) -> InferenceResult<Poly> {
+    infer_located(e, Gamma).map_err(|err| err.error)
+}
+
+#[allow(nonstandard_style)]
+pub fn infer_located<'a>(e: &'a Expr, Gamma: &Ctxt) -> Result<Poly, LocatedError<'a>> {
+    let mut algorithm = AlgorithmJ::new();
+    let tau = algorithm.infer(e, Gamma)?.tau().clone();
+    Ok(algorithm.generalize(tau, Gamma, e))
+}
+
+/// Infers the type of the expression, together with the substitution that inference applied to the free type
+/// variables of the context. The type is generalized with respect to the context refined by this substitution. The
+/// substitution may introduce fresh type variables into the context, which later inferences in the refined context
+/// avoid.
+#[allow(nonstandard_style)]
+pub fn infer_with_subst(e: &Expr, Gamma: &Ctxt) -> InferenceResult<(Poly, Subst)> {
     let mut algorithm = AlgorithmJ::new();
-    let tau = algorithm
-        .infer(e, Gamma)?
-        .canonicalize(&algorithm.aliases)
-        .generalize(Gamma);
-    Ok(tau)
+    let tau = algorithm.infer(e, Gamma).map_err(|err| err.error)?.tau().clone();
+    let sigma = algorithm.generalize(tau, Gamma, e);
+    Ok((sigma, algorithm.context_subst(Gamma)))
+}
+
+/// Infers the type of the expression, allowing equi-recursive types: instead of failing the occurs check, unifying
+/// a type variable with a type containing it makes the variable a recursive type, so that, e.g., `λ x . x x` has the
+/// type `∀ a . (μ b . b → a) → a`.
+#[allow(nonstandard_style)]
+pub fn infer_equirecursive(e: &Expr, Gamma: &Ctxt) -> InferenceResult<Poly> {
+    let mut algorithm = AlgorithmJ {
+        equirecursive: true,
+        ..AlgorithmJ::new()
+    };
+    let tau = algorithm.infer(e, Gamma).map_err(|err| err.error)?.tau().clone();
+    Ok(algorithm.generalize(tau, Gamma, e))
+}
+
+/// Infers the type of the expression while recording every step taken.
+#[allow(nonstandard_style)]
+pub fn infer_traced<'a>(e: &'a Expr, Gamma: &Ctxt) -> (Result<Poly, LocatedError<'a>>, Trace<'a>) {
+    let mut algorithm = AlgorithmJ::tracing();
+    let result = algorithm
+        .infer(e, Gamma)
+        .map(|e_typed| algorithm.generalize(e_typed.tau().clone(), Gamma, e));
+    (result, algorithm.trace.unwrap_or_default())
+}
+
+/// Infers the type of every sub-expression. The resulting types are only generalized at `let` expressions, so
+/// the type of the whole expression is a monotype.
+#[allow(nonstandard_style)]
+pub fn infer_typed(e: &Expr, Gamma: &Ctxt) -> InferenceResult<TypedExpr> {
+    infer_typed_with_subst(e, Gamma).map(|(e, _)| e)
+}
+
+/// Like [`infer_typed`], but also returns the substitution applied to the free type variables of the context. The
+/// types in the tree are valid in the context refined by this substitution.
+#[allow(nonstandard_style)]
+pub fn infer_typed_with_subst(e: &Expr, Gamma: &Ctxt) -> InferenceResult<(TypedExpr, Subst)> {
+    let mut algorithm = AlgorithmJ::new();
+    let e = algorithm.infer(e, Gamma).map_err(|err| err.error)?;
+    Ok((e.canonicalize(&algorithm.aliases), algorithm.context_subst(Gamma)))
+}
+
+/// Infers a type even if the expression contains type errors, by giving each sub-expression at which an error is
+/// detected a fresh type and continuing. Returns the resulting partial typing together with all errors encountered.
+#[allow(nonstandard_style)]
+pub fn infer_recovering(e: &Expr, Gamma: &Ctxt) -> (Poly, Vec<InferenceError>) {
+    let (sigma, _, errors) = infer_recovering_typed(e, Gamma);
+    (sigma, errors.into_iter().map(|err| err.error).collect())
+}
+
+/// Like [`infer_recovering`], but also returns the type of every sub-expression, and the sub-expressions at which
+/// the errors were detected.
+#[allow(nonstandard_style)]
+pub fn infer_recovering_typed<'a>(e: &'a Expr, Gamma: &Ctxt) -> (Poly, TypedExpr, Vec<LocatedError<'a>>) {
+    let (sigma, _, e_typed, errors) = infer_recovering_with_subst(e, Gamma);
+    (sigma, e_typed, errors)
 }
 
-struct AlgorithmJ {
+/// Like [`infer_recovering_typed`], but also returns the substitution applied to the free type variables of the
+/// context. If there are no errors, the type and the substitution are those returned by [`infer_with_subst`], and
+/// otherwise the first error is the one it fails with.
+#[allow(nonstandard_style)]
+pub fn infer_recovering_with_subst<'a>(e: &'a Expr, Gamma: &Ctxt) -> (Poly, Subst, TypedExpr, Vec<LocatedError<'a>>) {
+    let mut algorithm = AlgorithmJ::recovering();
+    let e_typed = algorithm.infer(e, Gamma).expect("errors are recovered from");
+    let sigma = algorithm.generalize(e_typed.tau().clone(), Gamma, e);
+    let errors = algorithm.errors.take().unwrap_or_default();
+    let S = algorithm.context_subst(Gamma);
+    (sigma, S, e_typed.canonicalize(&algorithm.aliases), errors)
+}
+
+struct AlgorithmJ<'a> {
     counter: u32,
     aliases: HashMap<TypeVar, Mono>,
+    errors: Option<Vec<LocatedError<'a>>>,
+    trace: Option<Trace<'a>>,
+    equirecursive: bool,
+    /// The sub-expressions currently being processed, innermost last.
+    exprs: Vec<&'a Expr>,
 }
 
-impl AlgorithmJ {
-    pub fn new() -> AlgorithmJ {
+impl<'a> AlgorithmJ<'a> {
+    pub fn new() -> AlgorithmJ<'a> {
         AlgorithmJ {
             counter: 0,
             aliases: HashMap::new(),
+            errors: None,
+            trace: None,
+            equirecursive: false,
+            exprs: Vec::new(),
+        }
+    }
+
+    pub fn recovering() -> AlgorithmJ<'a> {
+        AlgorithmJ {
+            errors: Some(Vec::new()),
+            ..AlgorithmJ::new()
         }
     }
 
+    pub fn tracing() -> AlgorithmJ<'a> {
+        AlgorithmJ {
+            trace: Some(Trace::default()),
+            ..AlgorithmJ::new()
+        }
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn infer(&mut self, e: &'a Expr, Gamma: &Ctxt) -> Result<TypedExpr, LocatedError<'a>> {
+        if self.exprs.is_empty() {
+            // The context may contain fresh type variables of an earlier inference, e.g., after applying the
+            // substitution returned by `infer_with_subst`, which new ones must not clash with.
+            self.counter = self.counter.max(last_fresh_var(Gamma));
+        }
+        self.exprs.push(e);
+        let result = self.infer_expr(e, Gamma);
+        self.exprs.pop();
+        result
+    }
+
     #[allow(nonstandard_style)]
-    pub fn infer(&mut self, e: &Expr, Gamma: &Ctxt) -> InferenceResult<Mono> {
+    fn infer_expr(&mut self, e: &'a Expr, Gamma: &Ctxt) -> Result<TypedExpr, LocatedError<'a>> {
         match e {
-            Expr::Var(x) => {
-                let sigma = Gamma.get(x).ok_or_else(|| InferenceError::UnknownVar(x.into()))?;
-                let tau = sigma.clone().inst(self.new_vars());
-                Ok(tau)
-            }
+            Expr::Var(x) => match Gamma.get(x) {
+                Some(sigma) => {
+                    let S = sigma.instantiation(self.new_vars());
+                    let Poly(_, tau) = sigma;
+                    let tau = tau.clone().apply(&S);
+                    if sigma.as_mono().is_none() {
+                        self.record(Event::Instantiate(sigma.clone(), tau.clone()), e);
+                    }
+                    Ok(TypedExpr::Var(x.clone(), S, tau))
+                }
+                None => {
+                    let root = self.exprs[0];
+                    self.fail(InferenceError::unknown_var(x, Gamma).in_expr(root).at(e))?;
+                    Ok(TypedExpr::Var(x.clone(), Subst::new(), self.new_var()))
+                }
+            },
             Expr::App(e0, e1) => {
-                let tau0 = self.infer(e0, Gamma)?;
-                let tau1 = self.infer(e1, Gamma)?;
+                let e0 = self.infer(e0, Gamma)?;
+                let e1 = self.infer(e1, Gamma)?;
                 let tau_prime = self.new_var();
-                self.unify(tau0, Mono::arrow(tau1, tau_prime.clone()))?;
-                Ok(tau_prime)
+                if let Err(error) = self.unify(e0.tau().clone(), Mono::arrow(e1.tau().clone(), tau_prime.clone())) {
+                    self.fail(error.at(e))?;
+                }
+                Ok(TypedExpr::app(e0, e1, tau_prime))
             }
             Expr::Abs(x, e) => {
                 let tau = self.new_var();
                 let Gamma_prime = Gamma.clone().bind(x, Poly::mono(tau.clone()));
-                let tau_prime = self.infer(e, &Gamma_prime)?;
-                Ok(Mono::arrow(tau, tau_prime))
+                let e = self.infer(e, &Gamma_prime)?;
+                let tau_prime = e.tau().clone();
+                Ok(TypedExpr::abs(x, e, Mono::arrow(tau, tau_prime)))
             }
             Expr::Let(x, e0, e1) => {
-                let tau = self.infer(e0, Gamma)?.canonicalize(&self.aliases).generalize(Gamma);
-                let Gamma_prime = Gamma.clone().bind(x, tau);
-                let tau_prime = self.infer(e1, &Gamma_prime)?;
-                Ok(tau_prime)
+                let e0 = self.infer(e0, Gamma)?;
+                let sigma = self.generalize(e0.tau().clone(), Gamma, e);
+                let Gamma_prime = Gamma.clone().bind(x, sigma.clone());
+                let e1 = self.infer(e1, &Gamma_prime)?;
+                let tau_prime = e1.tau().clone();
+                Ok(TypedExpr::r#let(x, sigma, e0, e1, tau_prime))
+            }
+            Expr::Fold(annotation, e0) => {
+                let tau_rec = annotation.instantiate(|| self.new_var());
+                let e0 = self.infer(e0, Gamma)?;
+                if let Err(error) = self.unify(tau_rec.clone().unfold(), e0.tau().clone()) {
+                    self.fail(error.at(e))?;
+                }
+                Ok(TypedExpr::fold(annotation.clone(), e0, tau_rec))
+            }
+            Expr::Unfold(annotation, e0) => {
+                let tau_rec = annotation.instantiate(|| self.new_var());
+                let e0 = self.infer(e0, Gamma)?;
+                if let Err(error) = self.unify(tau_rec.clone(), e0.tau().clone()) {
+                    self.fail(error.at(e))?;
+                }
+                Ok(TypedExpr::unfold(annotation.clone(), e0, tau_rec.unfold()))
+            }
+            Expr::Error => Ok(TypedExpr::Error(self.new_var())),
+        }
+    }
+
+    #[allow(nonstandard_style)]
+    fn generalize(&mut self, tau: Mono, Gamma: &Ctxt, e: &'a Expr) -> Poly {
+        let sigma = tau
+            .canonicalize(&self.aliases)
+            .generalize(&Gamma.canonicalize(&self.aliases));
+        self.record(Event::Generalize(sigma.clone()), e);
+        sigma
+    }
+
+    /// The types the free type variables of the context have been unified with.
+    #[allow(nonstandard_style)]
+    fn context_subst(&self, Gamma: &Ctxt) -> Subst {
+        Gamma
+            .free()
+            .into_iter()
+            .filter_map(|alpha| {
+                let tau = Mono::Var(alpha.clone()).canonicalize(&self.aliases);
+                (tau != Mono::Var(alpha.clone())).then_some((alpha, tau))
+            })
+            .collect()
+    }
+
+    fn record(&mut self, event: Event, e: &'a Expr) {
+        if let Some(trace) = &mut self.trace {
+            trace.events.push((event, e));
+        }
+    }
+
+    /// Records the event at the sub-expression currently being processed.
+    fn record_current(&mut self, event: Event) {
+        if let Some(&e) = self.exprs.last() {
+            self.record(event, e);
+        }
+    }
+
+    /// Records the error if recovering from errors, and returns it otherwise.
+    fn fail(&mut self, err: LocatedError<'a>) -> Result<(), LocatedError<'a>> {
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(err);
+                Ok(())
             }
+            None => Err(err),
         }
     }
 
     fn new_var(&mut self) -> Mono {
         self.counter += 1;
         let alpha = format!("_{}", self.counter);
+        self.record_current(Event::NewVar(alpha.clone()));
         Mono::Var(alpha)
     }
 
@@ -80,14 +411,24 @@ impl AlgorithmJ {
 
     #[allow(nonstandard_style)]
     fn unify(&mut self, tau1: Mono, tau2: Mono) -> InferenceResult<()> {
+        if self.equirecursive {
+            return self.unify_equirecursive(tau1, tau2, &mut Vec::new());
+        }
+
         let tau1 = tau1.canonicalize(&self.aliases);
         let tau2 = tau2.canonicalize(&self.aliases);
+        self.record_current(Event::Unify(tau1.clone(), tau2.clone()));
 
         match (tau1, tau2) {
             (tau1, tau2) if tau1 == tau2 => Ok(()),
             (Mono::App(C1, taus1), Mono::App(C2, taus2)) if C1 == C2 && taus1.len() == taus2.len() => {
-                for (tau1, tau2) in taus1.into_iter().zip(taus2) {
-                    self.unify(tau1, tau2)?
+                let (tau1, tau2) = (Mono::App(C1, taus1.clone()), Mono::App(C2, taus2.clone()));
+                for (i, (sigma1, sigma2)) in taus1.into_iter().zip(taus2).enumerate() {
+                    self.unify(sigma1, sigma2).map_err(|err| {
+                        let tau1 = tau1.clone().canonicalize(&self.aliases);
+                        let tau2 = tau2.clone().canonicalize(&self.aliases);
+                        err.within(i, &tau1, &tau2)
+                    })?
                 }
                 Ok(())
             }
@@ -95,23 +436,243 @@ impl AlgorithmJ {
                 if tau.occurs(&alpha) {
                     Err(InferenceError::RecursiveType(tau, alpha))
                 } else {
+                    self.record_current(Event::Alias(alpha.clone(), tau.clone()));
                     self.aliases.insert(alpha, tau);
                     Ok(())
                 }
             }
-            (tau1, tau2) => Err(InferenceError::ImpossibleUnification(tau1, tau2)),
+            (tau1 @ Mono::Rec(..), tau2 @ Mono::Rec(..)) => {
+                // Recursive types are nominal: their bodies must be equal with the bound variables identified, so the
+                // common bound variable must neither be unified with another type nor escape into one.
+                let (Mono::Rec(alpha, body1), Mono::Rec(beta, body2)) = (&tau1, &tau2) else {
+                    unreachable!()
+                };
+                // The common variable must not be aliased, nor occur in the types aliased, already.
+                let (gamma, body1, body2) =
+                    open_recursive(alpha, body1.as_ref().clone(), beta, body2.as_ref().clone(), |gamma| {
+                        self.aliases.contains_key(gamma) || self.aliases.values().any(|tau| tau.occurs(gamma))
+                    });
+                // Unifying the bodies may alias variables before failing; these aliases are dropped again, so that
+                // recovering from the error leaves the types as they were.
+                let aliases = self.aliases.clone();
+                let unified = self.unify(body1, body2).is_ok();
+                if unified && !self.aliases.contains_key(&gamma) && !self.aliases.values().any(|tau| tau.occurs(&gamma))
+                {
+                    Ok(())
+                } else {
+                    self.aliases = aliases;
+                    Err(InferenceError::mismatch(tau1, tau2))
+                }
+            }
+            (tau1, tau2) => Err(InferenceError::mismatch(tau1, tau2)),
+        }
+    }
+
+    /// Unifies without an occurs check. As the aliases may then be cyclic, types are only resolved as far as needed to
+    /// see their outermost constructor, and the pairs of types already being unified are assumed to be equal.
+    #[allow(nonstandard_style)]
+    fn unify_equirecursive(&mut self, tau1: Mono, tau2: Mono, assumed: &mut Vec<(Mono, Mono)>) -> InferenceResult<()> {
+        let tau1 = self.resolve(tau1);
+        let tau2 = self.resolve(tau2);
+        if tau1 == tau2 || assumed.contains(&(tau1.clone(), tau2.clone())) {
+            return Ok(());
+        }
+        self.record_current(Event::Unify(
+            tau1.clone().canonicalize(&self.aliases),
+            tau2.clone().canonicalize(&self.aliases),
+        ));
+
+        match (tau1, tau2) {
+            (Mono::Var(alpha), tau) | (tau, Mono::Var(alpha)) => {
+                self.record_current(Event::Alias(alpha.clone(), tau.clone().canonicalize(&self.aliases)));
+                self.aliases.insert(alpha, tau);
+                Ok(())
+            }
+            (tau1 @ Mono::Rec(..), tau2) => {
+                assumed.push((tau1.clone(), tau2.clone()));
+                self.unify_equirecursive(tau1.unfold(), tau2, assumed)
+            }
+            (tau1, tau2 @ Mono::Rec(..)) => {
+                assumed.push((tau1.clone(), tau2.clone()));
+                self.unify_equirecursive(tau1, tau2.unfold(), assumed)
+            }
+            (Mono::App(C1, taus1), Mono::App(C2, taus2)) if C1 == C2 && taus1.len() == taus2.len() => {
+                assumed.push((Mono::App(C1, taus1.clone()), Mono::App(C2, taus2.clone())));
+                for (tau1, tau2) in taus1.into_iter().zip(taus2) {
+                    self.unify_equirecursive(tau1, tau2, assumed)?
+                }
+                Ok(())
+            }
+            (tau1, tau2) => Err(InferenceError::mismatch(
+                tau1.canonicalize(&self.aliases),
+                tau2.canonicalize(&self.aliases),
+            )),
+        }
+    }
+
+    /// Follows the aliases of a type variable until reaching a type that is not an aliased variable.
+    fn resolve(&self, tau: Mono) -> Mono {
+        match tau {
+            Mono::Var(ref alpha) => match self.aliases.get(alpha) {
+                Some(tau) => self.resolve(tau.clone()),
+                None => tau,
+            },
+            tau => tau,
         }
     }
 }
 
+/// The largest `n` such that the type variable `_n` is free in the context, or 0 if there is none.
+#[allow(nonstandard_style)]
+fn last_fresh_var(Gamma: &Ctxt) -> u32 {
+    Gamma
+        .free()
+        .iter()
+        .filter_map(|alpha| alpha.strip_prefix('_')?.parse().ok())
+        .max()
+        .unwrap_or(0)
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::NewVar(alpha) => write!(f, "new variable {alpha}"),
+            Event::Unify(tau1, tau2) => write!(f, "unify {tau1} and {tau2}"),
+            Event::Alias(alpha, tau) => write!(f, "alias {alpha} := {tau}"),
+            Event::Instantiate(sigma, tau) => write!(f, "instantiate {sigma} as {tau}"),
+            Event::Generalize(sigma) => write!(f, "generalize to {sigma}"),
+        }
+    }
+}
+
+impl std::fmt::Display for Trace<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (event, expr) in &self.events {
+            writeln!(f, "{}    [{}]", event, expr)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
-mod tests {
-    use crate::parse::{parse, parse_poly};
+pub(crate) mod tests {
+    use crate::{
+        algorithm_m, algorithm_w,
+        parse::{parse, parse_poly, parse_recovering},
+    };
 
     use super::*;
 
     const EMPTY: Ctxt = Ctxt::new();
 
+    /// `(λ x . x x) (λ x . x x)`, typed with the help of a recursive type.
+    const OMEGA: &str = "(λ x . (unfold [μ t . t → a] x) x) (fold [μ t . t → a] (λ x . (unfold [μ t . t → a] x) x))";
+
+    /// A test case: the context as pairs of names and type schemes, the expression, and the expected type scheme or
+    /// error code.
+    type Case = (
+        &'static [(&'static str, &'static str)],
+        &'static str,
+        Result<&'static str, &'static str>,
+    );
+
+    const CASES: [Case; 20] = [
+        (&[("x", "C")], "x", Ok("C")),
+        (&[], "x", Err("E0001")),
+        (&[], "λ x . x", Ok("∀ a . a → a")),
+        (
+            &[("unify", "∀ x . x → x → x")],
+            "λ x . λ y . unify x y",
+            Ok("∀ a . a → a → a"),
+        ),
+        (
+            &[("unify", "∀ x . x → x → x"), ("n", "Int"), ("x", "a")],
+            "unify n x",
+            Ok("Int"),
+        ),
+        (
+            &[("unify", "∀ x . x → x → x"), ("n", "Int"), ("m", "Int")],
+            "unify n m",
+            Ok("Int"),
+        ),
+        (
+            &[("unify", "∀ x . x → x → x"), ("n", "Int"), ("s", "String")],
+            "unify n s",
+            Err("E0002"),
+        ),
+        (&[], "λ f . λ x . f x", Ok("∀ a b . (a → b) → a → b")),
+        (&[], "λ f . λ x . x f", Ok("∀ a b . a → (a → b) → b")),
+        (
+            &[],
+            "λ f . λ g . λ x . f (g x)",
+            Ok("∀ a b c . (b → c) → (a → b) → (a → c)"),
+        ),
+        (&[("n", "Int")], "let id = λ x . x in id n", Ok("Int")),
+        (&[], "λ f . let g = λ x . f x in g", Ok("∀ a b . (a → b) → a → b")),
+        (&[], "λ x . x x", Err("E0003")),
+        (&[], "λ x . (unfold [μ t . t → a] x) x", Ok("∀ a . (μ b . b → a) → a")),
+        (&[], OMEGA, Ok("∀ a . a")),
+        // The free variable of the annotation is instantiated anew at each use, so `a` can be both `Int` and `String`.
+        (
+            &[("n", "Int"), ("s", "String")],
+            "(λ x . λ y . y) (unfold [μ t . a] (fold [μ t . a] n)) (unfold [μ t . a] (fold [μ t . a] s))",
+            Ok("String"),
+        ),
+        (&[], "λ x . unfold [μ t . t → a] (fold [μ s . s → s] x)", Err("E0002")),
+        // Recursive types are equal up to the names of their bound variables.
+        (
+            &[],
+            "λ x . unfold [μ t . t → a] (fold [μ s . s → a] x)",
+            Ok("∀ a . ((μ b . b → a) → a) → (μ c . c → a) → a"),
+        ),
+        (
+            &[],
+            "λ g . (λ x . unfold [μ t . t → a] x) (fold [μ s . s → b] g)",
+            Ok("∀ a . ((μ b . b → a) → a) → (μ c . c → a) → a"),
+        ),
+        // Without `unfold`, the recursive type of `x` is not a function type.
+        (&[], "λ x . (fold [μ t . t → a] x) x", Err("E0002")),
+    ];
+
+    /// The test cases, parsed, for comparing other algorithms against this one.
+    #[allow(nonstandard_style)]
+    pub(crate) fn cases() -> Vec<(Ctxt, Expr, Result<Poly, &'static str>)> {
+        CASES
+            .iter()
+            .map(|(bindings, e, expected)| {
+                let Gamma = bindings.iter().fold(Ctxt::new(), |Gamma, (x, sigma)| {
+                    Gamma.bind(*x, parse_poly(sigma).unwrap())
+                });
+                let expected = expected.map(|sigma| parse_poly(sigma).unwrap());
+                (Gamma, parse(e).unwrap(), expected)
+            })
+            .collect()
+    }
+
+    /// Asserts that `infer` yields the same type as this algorithm, or the same kind of error, on every case.
+    #[allow(nonstandard_style)]
+    pub(crate) fn assert_agrees_with_algorithm_j(infer: impl Fn(&Expr, &Ctxt) -> InferenceResult<Poly>) {
+        for (Gamma, e, _) in cases() {
+            match (super::infer(&e, &Gamma), infer(&e, &Gamma)) {
+                (Ok(sigma1), Ok(sigma2)) => assert_eq!(sigma1, sigma2, "inferring {e}"),
+                (Err(err1), Err(err2)) => assert_eq!(
+                    std::mem::discriminant(&err1),
+                    std::mem::discriminant(&err2),
+                    "inferring {e}: {err1:?} vs. {err2:?}"
+                ),
+                (result1, result2) => panic!("inferring {e}: {result1:?} vs. {result2:?}"),
+            }
+        }
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_cases() {
+        for (Gamma, e, expected) in cases() {
+            assert_eq!(infer(&e, &Gamma).map_err(|err| err.code()), expected, "inferring {e}");
+        }
+    }
+
     #[test]
     #[allow(nonstandard_style)]
     fn test_var_from_context() {
@@ -125,10 +686,54 @@ mod tests {
     fn test_var_not_in_context() {
         assert_eq!(
             infer(&parse("x").unwrap(), &EMPTY),
-            Err(InferenceError::UnknownVar("x".into()))
+            Err(InferenceError::UnknownVar {
+                name: "x".into(),
+                candidates: Vec::new(),
+                bound_elsewhere: false,
+            })
         );
     }
 
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_suggest_similar_names() {
+        let Int = parse_poly("Int").unwrap();
+        let Gamma = Ctxt::new()
+            .bind("length", Int.clone())
+            .bind("lengths", Int.clone())
+            .bind("size", Int.clone());
+
+        let Err(InferenceError::UnknownVar { candidates, .. }) = infer(&parse("λ x . lenght x").unwrap(), &Gamma)
+        else {
+            panic!()
+        };
+
+        assert_eq!(candidates, ["length", "lengths"]);
+    }
+
+    #[test]
+    fn test_unknown_var_bound_elsewhere() {
+        let e = parse("(λ xs . xs) xs").unwrap();
+
+        for err in [
+            infer(&e, &EMPTY),
+            algorithm_w::infer(&e, &EMPTY),
+            algorithm_m::infer(&e, &EMPTY),
+        ] {
+            assert!(matches!(
+                err,
+                Err(InferenceError::UnknownVar {
+                    bound_elsewhere: true,
+                    ..
+                })
+            ));
+        }
+        assert!(matches!(
+            infer(&parse("λ xs . ys").unwrap(), &EMPTY),
+            Err(InferenceError::UnknownVar { bound_elsewhere: false, candidates, .. }) if candidates == ["xs"]
+        ));
+    }
+
     #[test]
     fn test_identity() {
         assert_eq!(
@@ -162,6 +767,41 @@ mod tests {
         assert_eq!(infer(&parse("unify n x").unwrap(), &Gamma), Ok(Int));
     }
 
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_unify_type_and_var_refines_context() {
+        let Unifier = parse_poly("∀ x . x → x → x").unwrap();
+        let Int = parse_poly("Int").unwrap();
+        let a = parse_poly("a").unwrap();
+
+        let Gamma = Ctxt::new().bind("unify", Unifier).bind("n", Int.clone()).bind("x", a);
+
+        let (sigma, S) = infer_with_subst(&parse("unify n x").unwrap(), &Gamma).unwrap();
+
+        assert_eq!(sigma, Int);
+        assert_eq!(S, Subst::from([("a".into(), Mono::nullary("Int"))]));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_thread_context_subst_through_definitions() {
+        let Int = parse_poly("Int").unwrap();
+
+        let Gamma = Ctxt::new()
+            .bind("unify", parse_poly("∀ x . x → x → x").unwrap())
+            .bind("n", Int.clone())
+            .bind("x", parse_poly("a").unwrap())
+            .bind("y", parse_poly("b").unwrap());
+
+        let (_, S) = infer_with_subst(&parse("unify x y").unwrap(), &Gamma).unwrap();
+        let Gamma = Gamma.apply(&S);
+        let (_, S) = infer_with_subst(&parse("unify y n").unwrap(), &Gamma).unwrap();
+        let Gamma = Gamma.apply(&S);
+
+        assert_eq!(Gamma.get(&"x".into()), Some(&Int));
+        assert_eq!(Gamma.get(&"y".into()), Some(&Int));
+    }
+
     #[test]
     #[allow(nonstandard_style)]
     fn test_unify_identical_types() {
@@ -187,10 +827,54 @@ mod tests {
 
         assert!(matches!(
             infer(&parse("unify n s").unwrap(), &Gamma),
-            Err(InferenceError::ImpossibleUnification(..))
+            Err(InferenceError::ImpossibleUnification { .. })
         ));
     }
 
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_fresh_vars_avoid_those_of_the_context() {
+        let Gamma = Ctxt::new()
+            .bind("x", parse_poly("a").unwrap())
+            .bind("n", parse_poly("Int").unwrap());
+
+        let (_, S) = infer_with_subst(&parse("x n").unwrap(), &Gamma).unwrap();
+        let Gamma = Gamma.apply(&S);
+        assert_eq!(
+            Gamma.get(&"x".into()),
+            Some(&Poly::mono(Mono::arrow(Mono::nullary("Int"), Mono::Var("_1".into()))))
+        );
+
+        let (sigma, S) = infer_with_subst(&parse("λ y . λ z . z").unwrap(), &Gamma).unwrap();
+        assert_eq!(sigma.normalize(), parse_poly("∀ a b . a → b → b").unwrap());
+        assert_eq!(S, Subst::new());
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_mismatch_reports_full_types() {
+        let Int = Mono::nullary("Int");
+        let String = Mono::nullary("String");
+        let Gamma = Ctxt::new()
+            .bind("f", parse_poly("(Int → Int) → Int").unwrap())
+            .bind("g", parse_poly("String → Int").unwrap());
+
+        let Err(InferenceError::ImpossibleUnification { expected, found, path }) =
+            infer(&parse("f g").unwrap(), &Gamma)
+        else {
+            panic!()
+        };
+
+        // The function's type is expected, and the type of the argument is found.
+        assert_eq!(
+            expected,
+            Mono::arrow(Mono::arrow(Int.clone(), Int.clone()), Int.clone())
+        );
+        assert!(matches!(&found, Mono::App(_, taus) if taus[0] == Mono::arrow(String.clone(), Int.clone())));
+        assert_eq!(*path, [0, 0]);
+        assert_eq!((expected.at(&path), found.at(&path)), (&Int, &String));
+    }
+
     #[test]
     fn test_apply() {
         assert_eq!(
@@ -225,6 +909,101 @@ mod tests {
         assert_eq!(infer(&parse("let id = λ x . x in id n").unwrap(), &Gamma), Ok(Int));
     }
 
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_error_location() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+        let e = parse("(λ f . f n) n").unwrap();
+
+        assert_eq!(infer_located(&e, &Gamma).unwrap_err().expr, &e);
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_recover_from_errors() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+        let e = parse("let a = n n in let b = y in λ x . x").unwrap();
+
+        let (sigma, errors) = infer_recovering(&e, &Gamma);
+
+        assert_eq!(sigma, parse_poly("∀ a . a → a").unwrap());
+        assert!(matches!(
+            &errors[..],
+            [InferenceError::ImpossibleUnification { .. }, InferenceError::UnknownVar { name, .. }] if name == "y"
+        ));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_recover_with_fresh_type() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+
+        let (sigma, errors) = infer_recovering(&parse("λ f . f y n").unwrap(), &Gamma);
+
+        assert_eq!(sigma, parse_poly("∀ a b . (a → Int → b) → b").unwrap());
+        assert!(matches!(&errors[..], [InferenceError::UnknownVar { name, .. }] if name == "y"));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_skip_syntax_errors() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+        let (e, _, _) = parse_recovering("λ f . f (λ x . ) n");
+
+        for sigma in [
+            infer(&e, &Gamma),
+            algorithm_w::infer(&e, &Gamma),
+            algorithm_m::infer(&e, &Gamma),
+        ] {
+            assert_eq!(sigma, Ok(parse_poly("∀ a b c . ((a → b) → Int → c) → c").unwrap()));
+        }
+        let (e, _, _) = parse_recovering("n (λ x . )");
+        assert!(matches!(
+            infer(&e, &Gamma),
+            Err(InferenceError::ImpossibleUnification { .. })
+        ));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_typed_expr() {
+        let Int = parse_poly("Int").unwrap();
+        let Gamma = Ctxt::new().bind("n", Int.clone());
+
+        let e = infer_typed(&parse("let id = λ x . x in id n").unwrap(), &Gamma).unwrap();
+
+        let TypedExpr::Let(_, sigma, id, body, tau) = e else {
+            panic!()
+        };
+        assert_eq!(sigma, parse_poly("∀ a . a → a").unwrap());
+        assert_eq!(id.to_string(), "(λx . (x : _1) : _1 → _1)");
+        assert_eq!(Poly::mono(tau), Int);
+
+        let TypedExpr::App(id, n, _) = *body else { panic!() };
+        let TypedExpr::Var(_, S, tau) = *id else { panic!() };
+        assert_eq!(tau.to_string(), "Int → Int");
+        assert_eq!(S.values().map(|tau| tau.to_string()).collect::<Vec<_>>(), ["Int"]);
+        assert_eq!(n.to_string(), "(n : Int)");
+    }
+
+    #[test]
+    fn test_typed_expr_is_canonical() {
+        let e = infer_typed(&parse("λ f . λ x . f x").unwrap(), &EMPTY).unwrap();
+
+        assert_eq!(
+            e.to_string(),
+            "(λf . (λx . ((f : _2 → _3) (x : _2) : _3) : _2 → _3) : (_2 → _3) → _2 → _3)"
+        );
+    }
+
+    #[test]
+    fn test_generalize_let_in_canonical_context() {
+        assert_eq!(
+            infer(&parse("λ f . let g = λ x . f x in g").unwrap(), &EMPTY),
+            Ok(parse_poly("∀ a b . (a → b) → a → b").unwrap())
+        )
+    }
+
     #[test]
     fn test_recursive_unification() {
         assert!(matches!(
@@ -232,4 +1011,177 @@ mod tests {
             Err(InferenceError::RecursiveType(..))
         ));
     }
+
+    #[test]
+    fn test_trace() {
+        let e = parse("let id = λ x . x in id id").unwrap();
+        let (result, trace) = infer_traced(&e, &EMPTY);
+
+        assert_eq!(result, infer_located(&e, &EMPTY));
+
+        let (events, origins): (Vec<_>, Vec<_>) = trace.events.into_iter().unzip();
+        let origins: Vec<String> = origins.into_iter().map(|e| e.to_string()).collect();
+        let id = parse_poly("∀ a . a → a").unwrap();
+        let var = |alpha: &str| Mono::Var(alpha.into());
+        let arrow = |alpha: &str, beta: &str| Mono::arrow(var(alpha), var(beta));
+        assert_eq!(
+            events,
+            [
+                Event::NewVar("_1".into()),
+                Event::Generalize(id.clone()),
+                Event::NewVar("_2".into()),
+                Event::Instantiate(id.clone(), arrow("_2", "_2")),
+                Event::NewVar("_3".into()),
+                Event::Instantiate(id.clone(), arrow("_3", "_3")),
+                Event::NewVar("_4".into()),
+                Event::Unify(arrow("_2", "_2"), Mono::arrow(arrow("_3", "_3"), var("_4"))),
+                Event::Unify(var("_2"), arrow("_3", "_3")),
+                Event::Alias("_2".into(), arrow("_3", "_3")),
+                Event::Unify(arrow("_3", "_3"), var("_4")),
+                Event::Alias("_4".into(), arrow("_3", "_3")),
+                Event::Generalize(parse_poly("∀ a . a → a").unwrap()),
+            ]
+        );
+        assert_eq!(
+            origins,
+            [
+                "λx . x",
+                "let id = λx . x in id id",
+                "id",
+                "id",
+                "id",
+                "id",
+                "id id",
+                "id id",
+                "id id",
+                "id id",
+                "id id",
+                "id id",
+                "let id = λx . x in id id",
+            ]
+        );
+        assert_eq!(
+            Event::Unify(var("_1"), arrow("_2", "_3")).to_string(),
+            "unify _1 and _2 → _3"
+        );
+    }
+
+    #[test]
+    fn test_trace_is_opt_in() {
+        let e = parse("λ x . x").unwrap();
+        let mut algorithm = AlgorithmJ::new();
+        algorithm.infer(&e, &EMPTY).unwrap();
+
+        assert!(algorithm.trace.is_none());
+    }
+
+    #[test]
+    fn test_equirecursive_self_application() {
+        let sigma = infer_equirecursive(&parse("λ x . x x").unwrap(), &EMPTY).unwrap();
+
+        assert_eq!(sigma.normalize().to_string(), "∀ a . (μ b . b → a) → a");
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_equirecursive_agrees_on_non_recursive_types() {
+        for (Gamma, e, expected) in cases() {
+            if let Ok(sigma) = expected {
+                // Equi-recursive types may keep recursive types such as `μ t . String` that are equal to their unfolding.
+                let Poly(_, tau) = infer_equirecursive(&e, &Gamma).unwrap().normalize();
+                let Poly(_, expected) = sigma.normalize();
+                assert!(tau.equivalent(&expected), "inferring {e}: {tau} vs. {expected}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_equirecursive_unifies_unfoldings() {
+        // `f` is applied to itself and to `f f`, so the types of both must be unified, which requires unfolding.
+        let sigma = infer_equirecursive(&parse("λ f . f (f f)").unwrap(), &EMPTY).unwrap();
+        let Poly(_, tau) = sigma.normalize();
+        let Mono::App(_, taus) = &tau else { panic!() };
+
+        assert!(matches!(taus[0], Mono::Rec(..)));
+        assert!(taus[0].equivalent(&taus[1]));
+        assert!(taus[1].equivalent(&taus[0].clone().unfold()));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_equirecursive_still_rejects_constructor_mismatch() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+
+        assert!(matches!(
+            infer_equirecursive(&parse("n n").unwrap(), &Gamma),
+            Err(InferenceError::ImpossibleUnification { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unfold_self_application() {
+        let sigma = infer(&parse("λ x . (unfold [μ t . t → a] x) x").unwrap(), &EMPTY).unwrap();
+
+        assert_eq!(sigma.normalize().to_string(), "∀ a . (μ b . b → a) → a");
+    }
+
+    #[test]
+    fn test_fold_omega() {
+        let sigma = infer(&parse(OMEGA).unwrap(), &EMPTY).unwrap();
+
+        assert_eq!(sigma.normalize().to_string(), "∀ a . a");
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_fold_instantiates_annotation() {
+        // The free variable of the annotation is instantiated anew at each use, so `a` can be both `Int` and `String`.
+        let Gamma = Ctxt::new()
+            .bind("n", parse_poly("Int").unwrap())
+            .bind("s", parse_poly("String").unwrap());
+        let e = parse("(λ x . λ y . y) (unfold [μ t . a] (fold [μ t . a] n)) (unfold [μ t . a] (fold [μ t . a] s))");
+
+        assert_eq!(infer(&e.unwrap(), &Gamma), Ok(parse_poly("String").unwrap()));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_recursive_mismatch_leaves_no_aliases() {
+        // Unifying the bodies aliases `a` with `Int` before failing, which must not affect the type of `x`.
+        let Gamma = Ctxt::new()
+            .bind("unify", parse_poly("∀ x . x → x → x").unwrap())
+            .bind("f", parse_poly("Int → Int").unwrap())
+            .bind("x", parse_poly("μ t . a → t").unwrap());
+        let e = parse("let r = unify x (fold [μ s . Int → Int] f) in x").unwrap();
+
+        let (sigma, errors) = infer_recovering(&e, &Gamma);
+
+        assert_eq!(sigma, parse_poly("μ t . a → t").unwrap());
+        assert!(matches!(&errors[..], [InferenceError::ImpossibleUnification { .. }]));
+    }
+
+    #[test]
+    fn test_recursive_types_are_nominal() {
+        let e = parse("λ x . unfold [μ t . t → a] (fold [μ s . s → s] x)").unwrap();
+
+        assert!(matches!(
+            infer(&e, &EMPTY),
+            Err(InferenceError::ImpossibleUnification {
+                expected: Mono::Rec(..),
+                found: Mono::Rec(..),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_unfolding_still_requires_annotations() {
+        // Without `unfold`, the recursive type of `x` is not a function type.
+        let e = parse("λ x . (fold [μ t . t → a] x) x").unwrap();
+
+        assert!(matches!(
+            infer(&e, &EMPTY),
+            Err(InferenceError::ImpossibleUnification { .. })
+        ));
+    }
 }
@@ -0,0 +1,136 @@
+use crate::{
+    algorithm_j::{InferenceError, InferenceResult, LocatedError},
+    algorithm_w::unify,
+    ctxt::Ctxt,
+    expr::Expr,
+    types::{Mono, Poly, Subst, compose},
+};
+
+#[allow(nonstandard_style)]
+pub fn infer(e: &Expr, Gamma: &Ctxt) -> InferenceResult<Poly> {
+    infer_located(e, Gamma).map_err(|err| err.error)
+}
+
+#[allow(nonstandard_style)]
+pub fn infer_located<'a>(e: &'a Expr, Gamma: &Ctxt) -> Result<Poly, LocatedError<'a>> {
+    let mut algorithm = AlgorithmM::new();
+    let rho = algorithm.new_var();
+    let S = algorithm
+        .infer(e, Gamma, rho.clone())
+        .map_err(|err| err.error.in_expr(e).at(err.expr))?;
+    Ok(rho.apply(&S).generalize(&Gamma.apply(&S)))
+}
+
+/// Infers types top-down: every sub-expression is checked against the type its context expects of it.
+struct AlgorithmM {
+    counter: u32,
+}
+
+impl AlgorithmM {
+    pub fn new() -> AlgorithmM {
+        AlgorithmM { counter: 0 }
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn infer<'a>(&mut self, e: &'a Expr, Gamma: &Ctxt, rho: Mono) -> Result<Subst, LocatedError<'a>> {
+        match e {
+            Expr::Var(x) => {
+                let sigma = Gamma
+                    .get(x)
+                    .ok_or_else(|| InferenceError::unknown_var(x, Gamma).at(e))?;
+                let tau = sigma.clone().inst(self.new_vars());
+                unify(rho, tau).map_err(|error| error.at(e))
+            }
+            Expr::App(e0, e1) => {
+                let beta = self.new_var();
+                let S0 = self.infer(e0, Gamma, Mono::arrow(beta.clone(), rho))?;
+                let S1 = self.infer(e1, &Gamma.apply(&S0), beta.apply(&S0))?;
+                Ok(compose(&S1, &S0))
+            }
+            Expr::Abs(x, e0) => {
+                let beta0 = self.new_var();
+                let beta1 = self.new_var();
+                let S0 = unify(rho, Mono::arrow(beta0.clone(), beta1.clone())).map_err(|error| error.at(e))?;
+                let Gamma_prime = Gamma.apply(&S0).bind(x, Poly::mono(beta0.apply(&S0)));
+                let S1 = self.infer(e0, &Gamma_prime, beta1.apply(&S0))?;
+                Ok(compose(&S1, &S0))
+            }
+            Expr::Let(x, e0, e1) => {
+                let beta = self.new_var();
+                let S0 = self.infer(e0, Gamma, beta.clone())?;
+                let Gamma = Gamma.apply(&S0);
+                let sigma = beta.apply(&S0).generalize(&Gamma);
+                let Gamma_prime = Gamma.bind(x, sigma);
+                let S1 = self.infer(e1, &Gamma_prime, rho.apply(&S0))?;
+                Ok(compose(&S1, &S0))
+            }
+            Expr::Fold(tau_rec, e0) => {
+                let tau_rec = tau_rec.instantiate(|| self.new_var());
+                let S0 = unify(rho, tau_rec.clone()).map_err(|error| error.at(e))?;
+                let S1 = self.infer(e0, &Gamma.apply(&S0), tau_rec.unfold().apply(&S0))?;
+                Ok(compose(&S1, &S0))
+            }
+            Expr::Unfold(tau_rec, e0) => {
+                let tau_rec = tau_rec.instantiate(|| self.new_var());
+                let S0 = unify(rho, tau_rec.clone().unfold()).map_err(|error| error.at(e))?;
+                let S1 = self.infer(e0, &Gamma.apply(&S0), tau_rec.apply(&S0))?;
+                Ok(compose(&S1, &S0))
+            }
+            Expr::Error => Ok(Subst::new()),
+        }
+    }
+
+    fn new_var(&mut self) -> Mono {
+        self.counter += 1;
+        let alpha = format!("_{}", self.counter);
+        Mono::Var(alpha)
+    }
+
+    fn new_vars(&mut self) -> impl IntoIterator<Item = Mono> {
+        std::iter::from_fn(|| Some(self.new_var()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::tests::assert_agrees_with_algorithm_j,
+        parse::{parse, parse_poly},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_agrees_with_algorithm_j() {
+        assert_agrees_with_algorithm_j(infer);
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_error_location() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+        let e = parse("(λ f . f n) n").unwrap();
+        let Expr::App(_, n) = &e else { unreachable!() };
+
+        assert_eq!(infer_located(&e, &Gamma).unwrap_err().expr, n.as_ref());
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_error_location_in_abstraction() {
+        let Poly(_, Int) = parse_poly("Int").unwrap();
+        let G = Mono::arrow(Mono::arrow(Int.clone(), Int.clone()), Int);
+        let Gamma = Ctxt::new().bind("g", Poly::mono(G));
+        let e = parse("g (λ x . λ y . x)").unwrap();
+
+        let result = infer_located(&e, &Gamma);
+
+        assert!(matches!(
+            result,
+            Err(LocatedError {
+                error: InferenceError::ImpossibleUnification { .. },
+                expr: Expr::Abs(y, _),
+            }) if y == "y"
+        ));
+    }
+}
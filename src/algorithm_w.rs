@@ -0,0 +1,150 @@
+use crate::{
+    algorithm_j::{InferenceError, InferenceResult, LocatedError},
+    ctxt::Ctxt,
+    expr::Expr,
+    types::{Mono, Poly, Subst, compose, open_recursive},
+};
+
+#[allow(nonstandard_style)]
+pub fn infer(e: &Expr, Gamma: &Ctxt) -> InferenceResult<Poly> {
+    infer_located(e, Gamma).map_err(|err| err.error)
+}
+
+#[allow(nonstandard_style)]
+pub fn infer_located<'a>(e: &'a Expr, Gamma: &Ctxt) -> Result<Poly, LocatedError<'a>> {
+    let mut algorithm = AlgorithmW::new();
+    let (S, tau) = algorithm
+        .infer(e, Gamma)
+        .map_err(|err| err.error.in_expr(e).at(err.expr))?;
+    Ok(tau.generalize(&Gamma.apply(&S)))
+}
+
+struct AlgorithmW {
+    counter: u32,
+}
+
+impl AlgorithmW {
+    pub fn new() -> AlgorithmW {
+        AlgorithmW { counter: 0 }
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn infer<'a>(&mut self, e: &'a Expr, Gamma: &Ctxt) -> Result<(Subst, Mono), LocatedError<'a>> {
+        match e {
+            Expr::Var(x) => {
+                let sigma = Gamma
+                    .get(x)
+                    .ok_or_else(|| InferenceError::unknown_var(x, Gamma).at(e))?;
+                let tau = sigma.clone().inst(self.new_vars());
+                Ok((Subst::new(), tau))
+            }
+            Expr::App(e0, e1) => {
+                let (S0, tau0) = self.infer(e0, Gamma)?;
+                let (S1, tau1) = self.infer(e1, &Gamma.apply(&S0))?;
+                let tau_prime = self.new_var();
+                let S2 = unify(tau0.apply(&S1), Mono::arrow(tau1, tau_prime.clone())).map_err(|error| error.at(e))?;
+                Ok((compose(&S2, &compose(&S1, &S0)), tau_prime.apply(&S2)))
+            }
+            Expr::Abs(x, e) => {
+                let tau = self.new_var();
+                let Gamma_prime = Gamma.clone().bind(x, Poly::mono(tau.clone()));
+                let (S, tau_prime) = self.infer(e, &Gamma_prime)?;
+                Ok((S.clone(), Mono::arrow(tau.apply(&S), tau_prime)))
+            }
+            Expr::Let(x, e0, e1) => {
+                let (S0, tau) = self.infer(e0, Gamma)?;
+                let Gamma = Gamma.apply(&S0);
+                let sigma = tau.generalize(&Gamma);
+                let Gamma_prime = Gamma.bind(x, sigma);
+                let (S1, tau_prime) = self.infer(e1, &Gamma_prime)?;
+                Ok((compose(&S1, &S0), tau_prime))
+            }
+            Expr::Fold(tau_rec, e0) => {
+                let tau_rec = tau_rec.instantiate(|| self.new_var());
+                let (S0, tau) = self.infer(e0, Gamma)?;
+                let S1 = unify(tau_rec.clone().unfold(), tau).map_err(|error| error.at(e))?;
+                Ok((compose(&S1, &S0), tau_rec.apply(&S1)))
+            }
+            Expr::Unfold(tau_rec, e0) => {
+                let tau_rec = tau_rec.instantiate(|| self.new_var());
+                let (S0, tau) = self.infer(e0, Gamma)?;
+                let S1 = unify(tau_rec.clone(), tau).map_err(|error| error.at(e))?;
+                Ok((compose(&S1, &S0), tau_rec.unfold().apply(&S1)))
+            }
+            Expr::Error => Ok((Subst::new(), self.new_var())),
+        }
+    }
+
+    fn new_var(&mut self) -> Mono {
+        self.counter += 1;
+        let alpha = format!("_{}", self.counter);
+        Mono::Var(alpha)
+    }
+
+    fn new_vars(&mut self) -> impl IntoIterator<Item = Mono> {
+        std::iter::from_fn(|| Some(self.new_var()))
+    }
+}
+
+/// Computes the most general unifier of two types.
+#[allow(nonstandard_style)]
+pub fn unify(tau1: Mono, tau2: Mono) -> InferenceResult<Subst> {
+    match (tau1, tau2) {
+        (tau1, tau2) if tau1 == tau2 => Ok(Subst::new()),
+        (Mono::App(C1, taus1), Mono::App(C2, taus2)) if C1 == C2 && taus1.len() == taus2.len() => {
+            let (tau1, tau2) = (Mono::App(C1, taus1.clone()), Mono::App(C2, taus2.clone()));
+            let mut S = Subst::new();
+            for (i, (sigma1, sigma2)) in taus1.into_iter().zip(taus2).enumerate() {
+                let S_prime = unify(sigma1.apply(&S), sigma2.apply(&S))
+                    .map_err(|err| err.within(i, &tau1.clone().apply(&S), &tau2.clone().apply(&S)))?;
+                S = compose(&S_prime, &S);
+            }
+            Ok(S)
+        }
+        (Mono::Var(alpha), tau) | (tau, Mono::Var(alpha)) => {
+            if tau.occurs(&alpha) {
+                Err(InferenceError::RecursiveType(tau, alpha))
+            } else {
+                Ok(Subst::from([(alpha, tau)]))
+            }
+        }
+        (tau1 @ Mono::Rec(..), tau2 @ Mono::Rec(..)) => {
+            // Recursive types are nominal: their bodies must be equal with the bound variables identified, so the
+            // common bound variable must neither be substituted nor escape into the substitution.
+            let (Mono::Rec(alpha, body1), Mono::Rec(beta, body2)) = (&tau1, &tau2) else {
+                unreachable!()
+            };
+            let (gamma, body1, body2) =
+                open_recursive(alpha, body1.as_ref().clone(), beta, body2.as_ref().clone(), |_| false);
+            match unify(body1, body2) {
+                Ok(S) if !S.contains_key(&gamma) && !S.values().any(|tau| tau.occurs(&gamma)) => Ok(S),
+                _ => Err(InferenceError::mismatch(tau1, tau2)),
+            }
+        }
+        (tau1, tau2) => Err(InferenceError::mismatch(tau1, tau2)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{algorithm_j::tests::assert_agrees_with_algorithm_j, parse::parse_poly};
+
+    use super::*;
+
+    #[test]
+    fn test_agrees_with_algorithm_j() {
+        assert_agrees_with_algorithm_j(infer);
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_unify_composes_substitutions() {
+        let Poly(_, tau1) = parse_poly("a → b → a").unwrap();
+        let Poly(_, tau2) = parse_poly("b → Int → c").unwrap();
+
+        let S = unify(tau1.clone(), tau2.clone()).unwrap();
+
+        assert_eq!(tau1.apply(&S), parse_poly("Int → Int → Int").unwrap().1);
+        assert_eq!(tau2.apply(&S), parse_poly("Int → Int → Int").unwrap().1);
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::HashSet;
+
+use crate::{
+    algorithm_j::{InferenceError, InferenceResult},
+    algorithm_w::unify,
+    ctxt::{Ctxt, similar_names},
+    expr::{Expr, Var},
+    types::{Mono, Poly, Subst, TypeVar, compose},
+};
+
+#[allow(nonstandard_style)]
+pub fn infer(e: &Expr, Gamma: &Ctxt) -> InferenceResult<Poly> {
+    let constraints = generate(e, Gamma)?;
+    let S = SequentialSolver::new().solve(&constraints)?;
+    Ok(constraints.tau.apply(&S).generalize(&Gamma.apply(&S)))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Constraint {
+    /// `τ1 ≡ τ2`: the two types must be equal.
+    Equal(Mono, Mono),
+    /// `τ ≼ σ`: the type must be an instance of the type scheme taken from the context.
+    ExplicitInstance(Mono, Poly),
+    /// `τ1 ≤_M τ2`: the first type must be an instance of the second one, generalized over all type variables
+    /// that do not occur in the monomorphic types `M`. This is how `let`-bound variables are instantiated.
+    ImplicitInstance(Mono, Vec<Mono>, Mono),
+}
+
+/// The constraints generated for an expression, each tagged with the sub-expression it originates from, together
+/// with the type variable standing for the type of the whole expression.
+#[derive(Clone, Debug)]
+pub struct Constraints<'a> {
+    pub constraints: Vec<(Constraint, &'a Expr)>,
+    pub tau: Mono,
+    counter: u32,
+}
+
+impl<'a> Constraints<'a> {
+    /// Keeps only the constraints originating from one of the given sub-expressions.
+    pub fn restrict(&self, origins: &[&Expr]) -> Constraints<'a> {
+        Constraints {
+            constraints: self
+                .constraints
+                .iter()
+                .filter(|(_, origin)| origins.iter().any(|e| std::ptr::eq(*e, *origin)))
+                .cloned()
+                .collect(),
+            tau: self.tau.clone(),
+            counter: self.counter,
+        }
+    }
+
+    /// The sub-expressions the constraints originate from, in the order of their first constraint.
+    pub fn origins(&self) -> Vec<&'a Expr> {
+        let mut origins: Vec<&'a Expr> = Vec::new();
+        for (_, origin) in &self.constraints {
+            if !origins.iter().any(|e| std::ptr::eq(*e, *origin)) {
+                origins.push(origin);
+            }
+        }
+        origins
+    }
+}
+
+#[allow(nonstandard_style)]
+pub fn generate<'a>(e: &'a Expr, Gamma: &Ctxt) -> InferenceResult<Constraints<'a>> {
+    let mut generator = Generator::new(Gamma);
+    let tau = generator.generate(e).map_err(|err| err.in_expr(e))?;
+    Ok(Constraints {
+        constraints: generator.constraints,
+        tau,
+        counter: generator.counter,
+    })
+}
+
+/// Computes a minimal set of sub-expressions whose constraints are jointly unsatisfiable, i.e., such that dropping
+/// the constraints of any one of them makes the rest satisfiable. Returns `None` if the constraints are satisfiable.
+pub fn unsatisfiable_core<'a>(constraints: &Constraints<'a>) -> Option<Vec<&'a Expr>> {
+    let is_satisfiable = |origins: &[&Expr]| SequentialSolver::new().solve(&constraints.restrict(origins)).is_ok();
+
+    let mut core = constraints.origins();
+    if is_satisfiable(&core) {
+        return None;
+    }
+
+    let mut i = 0;
+    while i < core.len() {
+        let mut candidate = core.clone();
+        candidate.remove(i);
+        if is_satisfiable(&candidate) {
+            i += 1;
+        } else {
+            core = candidate;
+        }
+    }
+    Some(core)
+}
+
+/// What is known about a variable bound inside the expression.
+enum Assumption {
+    Lambda(Mono),
+    Let(Mono, Vec<Mono>),
+}
+
+#[allow(nonstandard_style)]
+struct Generator<'a, 'g> {
+    counter: u32,
+    Gamma: &'g Ctxt,
+    scope: Vec<(Var, Assumption)>,
+    monos: Vec<Mono>,
+    constraints: Vec<(Constraint, &'a Expr)>,
+}
+
+impl<'a, 'g> Generator<'a, 'g> {
+    #[allow(nonstandard_style)]
+    fn new(Gamma: &'g Ctxt) -> Generator<'a, 'g> {
+        Generator {
+            counter: 0,
+            Gamma,
+            scope: Vec::new(),
+            monos: Gamma.free().into_iter().map(Mono::Var).collect(),
+            constraints: Vec::new(),
+        }
+    }
+
+    fn generate(&mut self, e: &'a Expr) -> InferenceResult<Mono> {
+        match e {
+            Expr::Var(x) => {
+                let beta = self.new_var();
+                let assumption = self.scope.iter().rev().find(|(y, _)| x == y);
+                let constraint = match assumption {
+                    Some((_, Assumption::Lambda(tau))) => Constraint::Equal(beta.clone(), tau.clone()),
+                    Some((_, Assumption::Let(tau, monos))) => {
+                        Constraint::ImplicitInstance(beta.clone(), monos.clone(), tau.clone())
+                    }
+                    None => {
+                        let sigma = self.Gamma.get(x).ok_or_else(|| self.unknown_var(x))?;
+                        Constraint::ExplicitInstance(beta.clone(), sigma.clone())
+                    }
+                };
+                self.constraints.push((constraint, e));
+                Ok(beta)
+            }
+            Expr::App(e0, e1) => {
+                let tau0 = self.generate(e0)?;
+                let tau1 = self.generate(e1)?;
+                let tau_prime = self.new_var();
+                let constraint = Constraint::Equal(tau0, Mono::arrow(tau1, tau_prime.clone()));
+                self.constraints.push((constraint, e));
+                Ok(tau_prime)
+            }
+            Expr::Abs(x, e0) => {
+                let tau = self.new_var();
+                self.scope.push((x.clone(), Assumption::Lambda(tau.clone())));
+                self.monos.push(tau.clone());
+                let tau_prime = self.generate(e0);
+                self.monos.pop();
+                self.scope.pop();
+                Ok(Mono::arrow(tau, tau_prime?))
+            }
+            Expr::Let(x, e0, e1) => {
+                let tau = self.generate(e0)?;
+                self.scope.push((x.clone(), Assumption::Let(tau, self.monos.clone())));
+                let tau_prime = self.generate(e1);
+                self.scope.pop();
+                tau_prime
+            }
+            Expr::Fold(tau_rec, e0) => {
+                let tau_rec = tau_rec.instantiate(|| self.new_var());
+                let tau = self.generate(e0)?;
+                self.constraints
+                    .push((Constraint::Equal(tau_rec.clone().unfold(), tau), e));
+                Ok(tau_rec)
+            }
+            Expr::Unfold(tau_rec, e0) => {
+                let tau_rec = tau_rec.instantiate(|| self.new_var());
+                let tau = self.generate(e0)?;
+                self.constraints.push((Constraint::Equal(tau_rec.clone(), tau), e));
+                Ok(tau_rec.unfold())
+            }
+            Expr::Error => Ok(self.new_var()),
+        }
+    }
+
+    /// The error for a variable bound neither inside the expression nor in the context.
+    fn unknown_var(&self, x: &str) -> InferenceError {
+        let names = self.scope.iter().rev().map(|(y, _)| y).chain(self.Gamma.vars());
+        InferenceError::UnknownVar {
+            name: x.into(),
+            candidates: similar_names(x, names),
+            bound_elsewhere: false,
+        }
+    }
+
+    fn new_var(&mut self) -> Mono {
+        self.counter += 1;
+        let alpha = format!("_{}", self.counter);
+        Mono::Var(alpha)
+    }
+}
+
+pub trait Solver {
+    /// Computes a substitution satisfying all constraints.
+    fn solve(&mut self, constraints: &Constraints<'_>) -> InferenceResult<Subst>;
+}
+
+/// Solves the constraints in the order in which they were generated, which is the order in which Algorithm J
+/// performs the corresponding unifications. In particular, all constraints stemming from the definition of a
+/// `let`-bound variable are solved before the variable's type is generalized.
+pub struct SequentialSolver {
+    counter: u32,
+}
+
+impl SequentialSolver {
+    pub fn new() -> SequentialSolver {
+        SequentialSolver { counter: 0 }
+    }
+
+    fn new_var(&mut self) -> Mono {
+        self.counter += 1;
+        let alpha = format!("_{}", self.counter);
+        Mono::Var(alpha)
+    }
+
+    fn new_vars(&mut self) -> impl IntoIterator<Item = Mono> {
+        std::iter::from_fn(|| Some(self.new_var()))
+    }
+}
+
+impl Default for SequentialSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver for SequentialSolver {
+    #[allow(nonstandard_style)]
+    fn solve(&mut self, constraints: &Constraints<'_>) -> InferenceResult<Subst> {
+        self.counter = self.counter.max(constraints.counter);
+
+        let mut S = Subst::new();
+        for (constraint, _) in &constraints.constraints {
+            let (tau1, tau2) = match constraint {
+                Constraint::Equal(tau1, tau2) => (tau1.clone().apply(&S), tau2.clone().apply(&S)),
+                Constraint::ExplicitInstance(tau, sigma) => {
+                    (tau.clone().apply(&S), sigma.clone().apply(&S).inst(self.new_vars()))
+                }
+                Constraint::ImplicitInstance(tau1, monos, tau2) => {
+                    let monos: HashSet<TypeVar> = monos.iter().flat_map(|tau| tau.clone().apply(&S).free()).collect();
+                    let tau2 = tau2.clone().apply(&S);
+                    let sigma = Poly(&tau2.free() - &monos, tau2);
+                    (tau1.clone().apply(&S), sigma.inst(self.new_vars()))
+                }
+            };
+            let S_prime = unify(tau1, tau2)?;
+            S = compose(&S_prime, &S);
+        }
+        Ok(S)
+    }
+}
+
+impl std::fmt::Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constraint::Equal(tau1, tau2) => write!(f, "{} ≡ {}", tau1, tau2),
+            Constraint::ExplicitInstance(tau, sigma) => write!(f, "{} ≼ {}", tau, sigma),
+            Constraint::ImplicitInstance(tau1, monos, tau2) => {
+                write!(f, "{} ≤{{", tau1)?;
+                for (i, tau) in monos.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", tau)?;
+                }
+                write!(f, "}} {}", tau2)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Constraints<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (constraint, origin) in &self.constraints {
+            writeln!(f, "{}    [{}]", constraint, origin)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::tests::assert_agrees_with_algorithm_j,
+        parse::{parse, parse_poly},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_agrees_with_algorithm_j() {
+        assert_agrees_with_algorithm_j(infer);
+    }
+
+    #[test]
+    fn test_generate() {
+        let e = parse("λ f . λ x . f x").unwrap();
+        let constraints = generate(&e, &Ctxt::new()).unwrap();
+
+        assert_eq!(constraints.tau.to_string(), "_1 → _2 → _5");
+        assert_eq!(
+            constraints.to_string(),
+            "_3 ≡ _1    [f]\n_4 ≡ _2    [x]\n_3 ≡ _4 → _5    [f x]\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_let() {
+        let e = parse("λ y . let f = λ x . y in f f").unwrap();
+        let constraints = generate(&e, &Ctxt::new()).unwrap();
+
+        assert_eq!(
+            constraints.to_string(),
+            "_3 ≡ _1    [y]\n_4 ≤{_1} _2 → _3    [f]\n_5 ≤{_1} _2 → _3    [f]\n_4 ≡ _5 → _6    [f f]\n"
+        );
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_unsatisfiable_core() {
+        let Gamma = Ctxt::new()
+            .bind("unify", parse_poly("∀ x . x → x → x").unwrap())
+            .bind("n", parse_poly("Int").unwrap())
+            .bind("s", parse_poly("String").unwrap());
+        let e = parse("(λ z . z) (unify n s)").unwrap();
+
+        let constraints = generate(&e, &Gamma).unwrap();
+        let core = unsatisfiable_core(&constraints).unwrap();
+
+        let core: Vec<String> = core.into_iter().map(|e| e.to_string()).collect();
+        assert_eq!(core, ["unify", "n", "unify n", "s", "unify n s"]);
+    }
+
+    #[test]
+    fn test_unsatisfiable_core_is_minimal() {
+        let e = parse("λ f . λ x . let y = f x in f y y").unwrap();
+
+        let constraints = generate(&e, &Ctxt::new()).unwrap();
+        let core = unsatisfiable_core(&constraints).unwrap();
+
+        assert!(SequentialSolver::new().solve(&constraints.restrict(&core)).is_err());
+        for i in 0..core.len() {
+            let mut subset = core.clone();
+            subset.remove(i);
+            assert!(SequentialSolver::new().solve(&constraints.restrict(&subset)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_satisfiable_has_no_core() {
+        let e = parse("λ f . λ x . f x").unwrap();
+        let constraints = generate(&e, &Ctxt::new()).unwrap();
+
+        assert_eq!(unsatisfiable_core(&constraints), None);
+    }
+}
@@ -1,14 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
     expr::Var,
-    types::{Poly, TypeVar},
+    types::{Mono, Poly, Subst, TypeVar},
 };
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 struct Binding(Var, Poly);
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Ctxt(Vec<Binding>);
 
 impl Ctxt {
@@ -16,6 +16,11 @@ impl Ctxt {
         Ctxt(Vec::new())
     }
 
+    pub fn is_empty(&self) -> bool {
+        let Ctxt(bindings) = self;
+        bindings.is_empty()
+    }
+
     pub fn free(&self) -> HashSet<TypeVar> {
         let Ctxt(bindings) = self;
         bindings.iter().flat_map(|Binding(_, sigma)| sigma.free()).collect()
@@ -30,6 +35,38 @@ impl Ctxt {
             .next()
     }
 
+    #[allow(nonstandard_style)]
+    pub fn apply(&self, S: &Subst) -> Ctxt {
+        let Ctxt(bindings) = self;
+        Ctxt(
+            bindings
+                .iter()
+                .map(|Binding(x, sigma)| Binding(x.clone(), sigma.clone().apply(S)))
+                .collect(),
+        )
+    }
+
+    pub fn canonicalize(&self, aliases: &HashMap<TypeVar, Mono>) -> Ctxt {
+        let Ctxt(bindings) = self;
+        Ctxt(
+            bindings
+                .iter()
+                .map(|Binding(x, sigma)| Binding(x.clone(), sigma.clone().canonicalize(aliases)))
+                .collect(),
+        )
+    }
+
+    /// The bound variables, innermost first.
+    pub fn vars(&self) -> impl Iterator<Item = &Var> {
+        let Ctxt(bindings) = self;
+        bindings.iter().rev().map(|Binding(x, _)| x)
+    }
+
+    /// The bound variables whose names are similar to `x`, closest first.
+    pub fn similar(&self, x: &str) -> Vec<Var> {
+        similar_names(x, self.vars())
+    }
+
     pub fn bind(self, x: impl Into<Var>, sigma: Poly) -> Self {
         let Ctxt(mut bindings) = self;
         bindings.push(Binding(x.into(), sigma));
@@ -37,11 +74,47 @@ impl Ctxt {
     }
 }
 
+/// The names within a small edit distance of `x`, which grows with the length of `x`, closest first and without
+/// duplicates.
+pub(crate) fn similar_names<'a>(x: &str, names: impl IntoIterator<Item = &'a Var>) -> Vec<Var> {
+    let max_distance = (x.chars().count() + 1) / 3;
+    let mut similar: Vec<(usize, &Var)> = Vec::new();
+    for y in names {
+        let distance = edit_distance(x, y);
+        if distance <= max_distance && !similar.iter().any(|(_, z)| z == &y) {
+            similar.push((distance, y));
+        }
+    }
+    similar.sort_by_key(|(distance, _)| *distance);
+    similar.into_iter().map(|(_, y)| y.clone()).collect()
+}
+
+/// The number of characters to insert, delete, or replace, or of adjacent characters to swap, to turn one string into
+/// the other (the optimal string alignment distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = d[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = replace.min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
 impl std::fmt::Display for Ctxt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Ctxt(bindings) = self;
 
-        if bindings.len() == 0 {
+        if bindings.is_empty() {
             write!(f, "")
         } else {
             write!(f, "{}", bindings[0])?;
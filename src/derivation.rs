@@ -0,0 +1,414 @@
+mod render;
+
+use crate::{
+    algorithm_j::{InferenceResult, infer_typed_with_subst},
+    ctxt::Ctxt,
+    expr::Expr,
+    typed_expr::TypedExpr,
+    types::{ARROW, Mono, Poly},
+};
+
+/// The rules of the declarative Hindley-Milner type system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    Var,
+    App,
+    Abs,
+    Let,
+    Inst,
+    Gen,
+    Fold,
+    Unfold,
+    /// Stands for a syntax error, which has no typing rule, so that derivations containing it are never valid.
+    Error,
+}
+
+/// A typing judgement `Γ ⊢ e : σ`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Judgement {
+    pub ctxt: Ctxt,
+    pub expr: Expr,
+    pub sigma: Poly,
+}
+
+/// A derivation of a judgement by a rule from the derivations of the rule's premises. The side conditions of `Inst`
+/// and `Gen` are not premises but checked directly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Derivation {
+    pub conclusion: Judgement,
+    pub rule: Rule,
+    pub premises: Vec<Derivation>,
+}
+
+/// A step of a derivation that is not an instance of its rule.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidStep<'a> {
+    pub step: &'a Derivation,
+    pub reason: &'static str,
+}
+
+/// Infers the type of an expression with Algorithm J and builds a derivation of the resulting judgement. If inference
+/// refines free type variables of the context, the derivation is in the refined context.
+#[allow(nonstandard_style)]
+pub fn derive(e: &Expr, Gamma: &Ctxt) -> InferenceResult<Derivation> {
+    let (e, S) = infer_typed_with_subst(e, Gamma)?;
+    let Gamma = &Gamma.apply(&S);
+    let derivation = build(&e, Gamma);
+    let sigma = e.tau().clone().generalize(Gamma);
+    Ok(generalize(derivation, sigma, Gamma))
+}
+
+#[allow(nonstandard_style)]
+fn build(e: &TypedExpr, Gamma: &Ctxt) -> Derivation {
+    let expr = e.erase();
+    match e {
+        TypedExpr::Var(x, _, tau) => {
+            let sigma = Gamma.get(x).expect("variables are bound in the context").clone();
+            let derivation = Derivation::axiom(Gamma, expr, sigma.clone());
+            if sigma.as_mono().is_some() {
+                derivation
+            } else {
+                Derivation::new(Gamma, e.erase(), Poly::mono(tau.clone()), Rule::Inst, vec![derivation])
+            }
+        }
+        TypedExpr::App(e0, e1, tau) => {
+            let premises = vec![build(e0, Gamma), build(e1, Gamma)];
+            Derivation::new(Gamma, expr, Poly::mono(tau.clone()), Rule::App, premises)
+        }
+        TypedExpr::Abs(x, e0, tau) => {
+            let Mono::App(_, taus) = tau else {
+                unreachable!("abstractions have function types")
+            };
+            let Gamma_prime = Gamma.clone().bind(x, Poly::mono(taus[0].clone()));
+            let premises = vec![build(e0, &Gamma_prime)];
+            Derivation::new(Gamma, expr, Poly::mono(tau.clone()), Rule::Abs, premises)
+        }
+        TypedExpr::Let(x, sigma, e0, e1, tau) => {
+            let Gamma_prime = Gamma.clone().bind(x, sigma.clone());
+            let premises = vec![
+                generalize(build(e0, Gamma), sigma.clone(), Gamma),
+                build(e1, &Gamma_prime),
+            ];
+            Derivation::new(Gamma, expr, Poly::mono(tau.clone()), Rule::Let, premises)
+        }
+        TypedExpr::Fold(_, e0, tau) => {
+            let premises = vec![build(e0, Gamma)];
+            Derivation::new(Gamma, expr, Poly::mono(tau.clone()), Rule::Fold, premises)
+        }
+        TypedExpr::Unfold(_, e0, tau) => {
+            let premises = vec![build(e0, Gamma)];
+            Derivation::new(Gamma, expr, Poly::mono(tau.clone()), Rule::Unfold, premises)
+        }
+        TypedExpr::Error(tau) => Derivation::new(Gamma, expr, Poly::mono(tau.clone()), Rule::Error, Vec::new()),
+    }
+}
+
+#[allow(nonstandard_style)]
+fn generalize(derivation: Derivation, sigma: Poly, Gamma: &Ctxt) -> Derivation {
+    if sigma.as_mono().is_some() {
+        derivation
+    } else {
+        let expr = derivation.conclusion.expr.clone();
+        Derivation::new(Gamma, expr, sigma, Rule::Gen, vec![derivation])
+    }
+}
+
+impl Derivation {
+    #[allow(nonstandard_style)]
+    pub fn new(Gamma: &Ctxt, expr: Expr, sigma: Poly, rule: Rule, premises: Vec<Derivation>) -> Derivation {
+        Derivation {
+            conclusion: Judgement {
+                ctxt: Gamma.clone(),
+                expr,
+                sigma,
+            },
+            rule,
+            premises,
+        }
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn axiom(Gamma: &Ctxt, expr: Expr, sigma: Poly) -> Derivation {
+        Derivation::new(Gamma, expr, sigma, Rule::Var, Vec::new())
+    }
+
+    /// Checks that every step of the derivation is an instance of its rule, independently of how the derivation was
+    /// obtained.
+    pub fn check(&self) -> Result<(), InvalidStep<'_>> {
+        for premise in &self.premises {
+            premise.check()?;
+        }
+        self.check_step().map_err(|reason| InvalidStep { step: self, reason })
+    }
+
+    #[allow(nonstandard_style)]
+    fn check_step(&self) -> Result<(), &'static str> {
+        let Judgement {
+            ctxt: Gamma,
+            expr,
+            sigma,
+        } = &self.conclusion;
+        let premises: Vec<&Judgement> = self.premises.iter().map(|premise| &premise.conclusion).collect();
+
+        match (self.rule, expr, &premises[..]) {
+            (Rule::Var, Expr::Var(x), []) => match Gamma.get(x) {
+                Some(sigma_prime) if sigma_prime == sigma => Ok(()),
+                Some(_) => Err("the type differs from the one in the context"),
+                None => Err("the variable is not bound in the context"),
+            },
+            (Rule::App, Expr::App(e0, e1), [premise0, premise1]) => {
+                expect_premise(premise0, Gamma, e0)?;
+                expect_premise(premise1, Gamma, e1)?;
+                let (Some(tau0), Some(tau1), Some(tau_prime)) =
+                    (premise0.sigma.as_mono(), premise1.sigma.as_mono(), sigma.as_mono())
+                else {
+                    return Err("the types are not monotypes");
+                };
+                if tau0.alpha_equivalent(&Mono::arrow(tau1.clone(), tau_prime.clone())) {
+                    Ok(())
+                } else {
+                    Err("the function type does not match the types of argument and result")
+                }
+            }
+            (Rule::Abs, Expr::Abs(x, e), [premise]) => {
+                let Some(Mono::App(C, taus)) = sigma.as_mono() else {
+                    return Err("the type is not a monotype");
+                };
+                let [tau, tau_prime] = &taus[..] else {
+                    return Err("the type is not a function type");
+                };
+                if C != ARROW {
+                    return Err("the type is not a function type");
+                }
+                expect_premise(premise, &Gamma.clone().bind(x, Poly::mono(tau.clone())), e)?;
+                match premise.sigma.as_mono() {
+                    Some(tau_body) if tau_body.alpha_equivalent(tau_prime) => Ok(()),
+                    _ => Err("the result type differs from the type of the body"),
+                }
+            }
+            (Rule::Let, Expr::Let(x, e0, e1), [premise0, premise1]) => {
+                expect_premise(premise0, Gamma, e0)?;
+                expect_premise(premise1, &Gamma.clone().bind(x, premise0.sigma.clone()), e1)?;
+                if premise1.sigma == *sigma {
+                    Ok(())
+                } else {
+                    Err("the type differs from the type of the body")
+                }
+            }
+            (Rule::Inst, _, [premise]) => {
+                expect_premise(premise, Gamma, expr)?;
+                if premise.sigma.is_more_general_than(sigma) {
+                    Ok(())
+                } else {
+                    Err("the type is not an instance of the premise's type")
+                }
+            }
+            (Rule::Gen, _, [premise]) => {
+                expect_premise(premise, Gamma, expr)?;
+                let Poly(alphas, tau) = sigma;
+                let Poly(betas, tau_prime) = &premise.sigma;
+                if !tau.alpha_equivalent(tau_prime) || !betas.is_subset(alphas) {
+                    return Err("the type does not generalize the premise's type");
+                }
+                let free = Gamma.free();
+                if alphas.difference(betas).any(|alpha| free.contains(alpha)) {
+                    Err("a generalized type variable is free in the context")
+                } else {
+                    Ok(())
+                }
+            }
+            (Rule::Fold, Expr::Fold(annotation, e), [premise]) => {
+                expect_premise(premise, Gamma, e)?;
+                let (Some(tau), Some(tau_rec)) = (premise.sigma.as_mono(), sigma.as_mono()) else {
+                    return Err("the types are not monotypes");
+                };
+                expect_annotation_instance(annotation, tau_rec)?;
+                if tau.alpha_equivalent(&tau_rec.clone().unfold()) {
+                    Ok(())
+                } else {
+                    Err("the type of the folded expression is not the unfolding of the recursive type")
+                }
+            }
+            (Rule::Unfold, Expr::Unfold(annotation, e), [premise]) => {
+                expect_premise(premise, Gamma, e)?;
+                let (Some(tau_rec), Some(tau)) = (premise.sigma.as_mono(), sigma.as_mono()) else {
+                    return Err("the types are not monotypes");
+                };
+                expect_annotation_instance(annotation, tau_rec)?;
+                if tau.alpha_equivalent(&tau_rec.clone().unfold()) {
+                    Ok(())
+                } else {
+                    Err("the type is not the unfolding of the recursive type")
+                }
+            }
+            (Rule::Error, Expr::Error, []) => Err("syntax errors have no type"),
+            _ => Err("the rule does not apply to this expression or number of premises"),
+        }
+    }
+}
+
+fn expect_annotation_instance(annotation: &Mono, tau_rec: &Mono) -> Result<(), &'static str> {
+    let annotation = annotation.clone().generalize(&Ctxt::new());
+    if !matches!(tau_rec, Mono::Rec(..)) {
+        Err("the type is not a recursive type")
+    } else if !annotation.is_more_general_than(&Poly::mono(tau_rec.clone())) {
+        Err("the recursive type is not an instance of the annotation")
+    } else {
+        Ok(())
+    }
+}
+
+#[allow(nonstandard_style)]
+fn expect_premise(premise: &Judgement, Gamma: &Ctxt, e: &Expr) -> Result<(), &'static str> {
+    if premise.expr != *e {
+        Err("a premise is about the wrong expression")
+    } else if premise.ctxt != *Gamma {
+        Err("a premise has the wrong context")
+    } else {
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Judgement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Judgement { ctxt, expr, sigma } = self;
+        if ctxt.is_empty() {
+            write!(f, "⊢ {} : {}", expr, sigma)
+        } else {
+            write!(f, "{} ⊢ {} : {}", ctxt, expr, sigma)
+        }
+    }
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Rule::Var => "Var",
+            Rule::App => "App",
+            Rule::Abs => "Abs",
+            Rule::Let => "Let",
+            Rule::Inst => "Inst",
+            Rule::Gen => "Gen",
+            Rule::Fold => "Fold",
+            Rule::Unfold => "Unfold",
+            Rule::Error => "Error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::fmt::Display for InvalidStep<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let InvalidStep { step, reason } = self;
+        write!(
+            f,
+            "Invalid [{}] step concluding '{}': {}",
+            step.rule, step.conclusion, reason
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::{infer_with_subst, tests::cases},
+        parse::{parse, parse_poly},
+    };
+
+    use super::*;
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_derivations_check() {
+        for (Gamma, e, _) in cases() {
+            let Ok((sigma, S)) = infer_with_subst(&e, &Gamma) else {
+                continue;
+            };
+
+            let derivation = derive(&e, &Gamma).unwrap();
+
+            assert_eq!(derivation.check(), Ok(()), "checking derivation for {e}");
+            assert_eq!(derivation.conclusion.ctxt, Gamma.apply(&S));
+            assert_eq!(derivation.conclusion.sigma, sigma);
+        }
+    }
+
+    #[test]
+    fn test_derive_let() {
+        let derivation = derive(&parse("let id = λ x . x in id").unwrap(), &Ctxt::new()).unwrap();
+
+        assert_eq!(derivation.rule, Rule::Gen);
+        let [derivation] = &derivation.premises[..] else {
+            panic!()
+        };
+        assert_eq!(derivation.rule, Rule::Let);
+        let [definition, body] = &derivation.premises[..] else {
+            panic!()
+        };
+        assert_eq!(definition.rule, Rule::Gen);
+        assert_eq!(definition.premises[0].rule, Rule::Abs);
+        assert_eq!(body.rule, Rule::Inst);
+        assert_eq!(body.premises[0].rule, Rule::Var);
+        assert_eq!(
+            body.premises[0].conclusion.to_string(),
+            "id : ∀ _1 . _1 → _1 ⊢ id : ∀ _1 . _1 → _1"
+        );
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_check_rejects_invalid_derivations() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+        let Int = parse_poly("Int").unwrap();
+        let Bool = parse_poly("Bool").unwrap();
+
+        let var = Derivation::axiom(&Gamma, Expr::var("n"), Bool.clone());
+        assert!(var.check().is_err());
+
+        let var = Derivation::axiom(&Gamma, Expr::var("n"), Int.clone());
+        assert_eq!(var.check(), Ok(()));
+
+        let generalized = Derivation::new(
+            &Gamma,
+            Expr::var("n"),
+            parse_poly("∀ a . Int").unwrap(),
+            Rule::Gen,
+            vec![var],
+        );
+        assert_eq!(generalized.check(), Ok(()));
+
+        let Gamma = Ctxt::new().bind("x", parse_poly("a").unwrap());
+        let var = Derivation::axiom(&Gamma, Expr::var("x"), parse_poly("a").unwrap());
+        let generalized = Derivation::new(
+            &Gamma,
+            Expr::var("x"),
+            parse_poly("∀ a . a").unwrap(),
+            Rule::Gen,
+            vec![var],
+        );
+        assert_eq!(
+            generalized.check().unwrap_err().reason,
+            "a generalized type variable is free in the context"
+        );
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_check_rejects_invalid_instantiation() {
+        let Gamma = Ctxt::new().bind("id", parse_poly("∀ a . a → a").unwrap());
+
+        let var = Derivation::axiom(&Gamma, Expr::var("id"), parse_poly("∀ a . a → a").unwrap());
+        let inst = Derivation::new(
+            &Gamma,
+            Expr::var("id"),
+            parse_poly("Int → Bool").unwrap(),
+            Rule::Inst,
+            vec![var],
+        );
+
+        assert_eq!(
+            inst.check().unwrap_err().reason,
+            "the type is not an instance of the premise's type"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use crate::derivation::Derivation;
+
+impl Derivation {
+    /// Renders the derivation as a standalone LaTeX document typesetting the proof tree with `bussproofs`.
+    pub fn to_latex(&self) -> String {
+        let mut latex = String::from("\\documentclass{article}\n\\usepackage{bussproofs}\n\\begin{document}\n");
+        latex.push_str("\\begin{prooftree}\n");
+        self.write_latex(&mut latex);
+        latex.push_str("\\end{prooftree}\n\\end{document}\n");
+        latex
+    }
+
+    fn write_latex(&self, latex: &mut String) {
+        for premise in &self.premises {
+            premise.write_latex(latex);
+        }
+
+        let inference = match self.premises.len() {
+            0 => {
+                latex.push_str("\\AxiomC{}\n");
+                "UnaryInfC"
+            }
+            1 => "UnaryInfC",
+            2 => "BinaryInfC",
+            _ => unreachable!("no rule has more than two premises"),
+        };
+
+        latex.push_str(&format!("\\RightLabel{{\\scriptsize {}}}\n", self.rule));
+        latex.push_str(&format!(
+            "\\{}{{${}$}}\n",
+            inference,
+            to_math(&self.conclusion.to_string())
+        ));
+    }
+
+    /// Renders the derivation as a proof tree in plain text, with premises above a line and the conclusion below.
+    pub fn to_tree(&self) -> String {
+        let mut tree: String = self
+            .tree_lines()
+            .0
+            .into_iter()
+            .map(|line| line.trim_end().to_string() + "\n")
+            .collect();
+        tree.pop();
+        tree
+    }
+
+    /// The lines of the proof tree, together with the width of the tree without the label of its last rule.
+    fn tree_lines(&self) -> (Vec<String>, usize) {
+        const GAP: &str = "   ";
+
+        let premises: Vec<(Vec<String>, usize)> = self.premises.iter().map(Derivation::tree_lines).collect();
+        let height = premises.iter().map(|(lines, _)| lines.len()).max().unwrap_or(0);
+
+        let mut lines: Vec<String> = (0..height)
+            .map(|row| {
+                premises
+                    .iter()
+                    .map(|(lines, _)| {
+                        let line = (row + lines.len())
+                            .checked_sub(height)
+                            .map_or("", |row| lines[row].as_str());
+                        pad(line, width(lines))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(GAP)
+            })
+            .collect();
+
+        let premises_width = match premises.split_last() {
+            Some(((_, last_width), others)) => {
+                others.iter().map(|(lines, _)| width(lines) + GAP.len()).sum::<usize>() + last_width
+            }
+            None => 0,
+        };
+        let conclusion = self.conclusion.to_string();
+        let conclusion_width = conclusion.chars().count();
+        let total_width = premises_width.max(conclusion_width);
+
+        let indent = " ".repeat((total_width - premises_width) / 2);
+        for line in &mut lines {
+            line.insert_str(0, &indent);
+        }
+        lines.push(format!("{} [{}]", "─".repeat(total_width), self.rule));
+        lines.push(format!(
+            "{}{}",
+            " ".repeat((total_width - conclusion_width) / 2),
+            conclusion
+        ));
+        (lines, total_width)
+    }
+}
+
+fn width(lines: &[String]) -> usize {
+    lines.iter().map(|line| line.chars().count()).max().unwrap_or(0)
+}
+
+fn pad(line: &str, width: usize) -> String {
+    format!("{}{}", line, " ".repeat(width - line.chars().count()))
+}
+
+/// Typesets the text in math mode: symbols become commands, keywords are set in sans-serif, type constructors upright,
+/// and all other identifiers in italics.
+fn to_math(text: &str) -> String {
+    let mut math = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !is_identifier_char(c) {
+            math.push_str(match c {
+                '⊢' => "\\vdash",
+                'λ' => "\\lambda ",
+                'μ' => "\\mu",
+                '∀' => "\\forall",
+                '→' => "\\to",
+                _ => {
+                    math.push(c);
+                    continue;
+                }
+            });
+            continue;
+        }
+
+        let mut identifier = String::from(c);
+        while let Some(&c) = chars.peek().filter(|&&c| is_identifier_char(c)) {
+            identifier.push(c);
+            chars.next();
+        }
+        let font = match identifier.as_str() {
+            "let" | "in" | "fold" | "unfold" => "\\mathsf",
+            _ if c.is_uppercase() => "\\mathrm",
+            _ => "\\mathit",
+        };
+        let letters: String = identifier.chars().map(to_math_letter).collect();
+        if identifier.chars().all(|c| c.is_ascii() || greek_letter(c).is_some()) {
+            math.push_str(&format!("{font}{{{letters}}}"));
+        } else {
+            // Other letters are only available in text mode.
+            math.push_str(&format!("\\textit{{{letters}}}"));
+        }
+    }
+    math
+}
+
+fn is_identifier_char(c: char) -> bool {
+    (c.is_alphanumeric() || c == '_' || c == '\'') && !matches!(c, 'λ' | 'μ')
+}
+
+fn to_math_letter(c: char) -> String {
+    match (c, greek_letter(c)) {
+        ('_', _) => String::from("\\_"),
+        (_, Some(command)) => format!("\\{command} "),
+        (c, None) => c.to_string(),
+    }
+}
+
+/// The command for a Greek letter that has one.
+fn greek_letter(c: char) -> Option<&'static str> {
+    let command = match c {
+        'α' => "alpha",
+        'β' => "beta",
+        'γ' => "gamma",
+        'δ' => "delta",
+        'ε' => "epsilon",
+        'ζ' => "zeta",
+        'η' => "eta",
+        'θ' => "theta",
+        'ι' => "iota",
+        'κ' => "kappa",
+        'ν' => "nu",
+        'ξ' => "xi",
+        'π' => "pi",
+        'ρ' => "rho",
+        'σ' => "sigma",
+        'ς' => "varsigma",
+        'τ' => "tau",
+        'υ' => "upsilon",
+        'φ' => "phi",
+        'χ' => "chi",
+        'ψ' => "psi",
+        'ω' => "omega",
+        'Γ' => "Gamma",
+        'Δ' => "Delta",
+        'Θ' => "Theta",
+        'Λ' => "Lambda",
+        'Ξ' => "Xi",
+        'Π' => "Pi",
+        'Σ' => "Sigma",
+        'Υ' => "Upsilon",
+        'Φ' => "Phi",
+        'Ψ' => "Psi",
+        'Ω' => "Omega",
+        _ => return None,
+    };
+    Some(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ctxt::Ctxt,
+        derivation::derive,
+        parse::{parse, parse_poly},
+    };
+
+    #[test]
+    fn test_to_tree() {
+        let derivation = derive(&parse("λ x . x").unwrap(), &Ctxt::new()).unwrap();
+
+        assert_eq!(
+            derivation.to_tree(),
+            [
+                "    ─────────────── [Var]",
+                "    x : _1 ⊢ x : _1",
+                "   ────────────────── [Abs]",
+                "   ⊢ λx . x : _1 → _1",
+                "───────────────────────── [Gen]",
+                "⊢ λx . x : ∀ _1 . _1 → _1",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_to_tree_with_two_premises() {
+        let derivation = derive(&parse("λ f . λ x . f x").unwrap(), &Ctxt::new()).unwrap();
+
+        let tree = derivation.to_tree();
+        let lines: Vec<&str> = tree.lines().collect();
+
+        assert_eq!(
+            lines[1],
+            "f : _2 → _3, x : _2 ⊢ f : _2 → _3         f : _2 → _3, x : _2 ⊢ x : _2"
+        );
+        assert!(lines[2].ends_with("─ [App]"));
+        assert_eq!(lines[3], "                    f : _2 → _3, x : _2 ⊢ f x : _3");
+    }
+
+    #[test]
+    fn test_to_latex() {
+        let derivation = derive(&parse("λ x . x").unwrap(), &Ctxt::new()).unwrap();
+
+        let latex = derivation.to_latex();
+
+        assert!(latex.starts_with("\\documentclass{article}\n\\usepackage{bussproofs}\n"));
+        assert!(latex.contains(
+            "\\AxiomC{}\n\\RightLabel{\\scriptsize Var}\n\\UnaryInfC{$\\mathit{x} : \\mathit{\\_1} \\vdash \\mathit{x} : \\mathit{\\_1}$}\n\
+             \\RightLabel{\\scriptsize Abs}\n\\UnaryInfC{$\\vdash \\lambda \\mathit{x} . \\mathit{x} : \\mathit{\\_1} \\to \\mathit{\\_1}$}\n"
+        ));
+        assert!(latex.ends_with("\\end{prooftree}\n\\end{document}\n"));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_to_latex_recursive_types_and_identifiers() {
+        let derivation = derive(&parse("λ x . (unfold [μ t . t → a] x) x").unwrap(), &Ctxt::new()).unwrap();
+
+        let latex = derivation.to_latex();
+
+        assert!(latex.contains("\\mathsf{unfold} [\\mu \\mathit{t} . \\mathit{t} \\to "));
+        assert!(!latex.contains('μ'));
+
+        let Gamma = Ctxt::new().bind("n", parse_poly("Σ").unwrap());
+        let derivation = derive(&parse("let α' = λ é . é in α' n").unwrap(), &Gamma).unwrap();
+
+        let latex = derivation.to_latex();
+
+        assert!(latex.contains("\\mathsf{let} \\mathit{\\alpha '} = \\lambda \\textit{é} . \\textit{é} \\mathsf{in}"));
+        assert!(latex.contains("\\mathit{n} : \\mathrm{\\Sigma }"));
+        assert!(!latex.contains(['α', 'Σ', 'λ', '→']));
+    }
+}
@@ -0,0 +1,387 @@
+use std::fmt::Write;
+
+use crate::{
+    algorithm_j::{InferenceError, LocatedError},
+    expr::Expr,
+    parse::ParseError,
+    span::{Span, SpanTree},
+    typed_expr::TypedExpr,
+    types::{ARROW, Mono},
+};
+
+/// A message attached to a span of the source text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// An error together with labeled spans of the source text, rendered in the style of rustc.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The stable code of the error, which `explain::explain` describes at length.
+    pub code: &'static str,
+    pub message: String,
+    /// Where the error was detected.
+    pub primary: Label,
+    /// Related spans, e.g., the sub-expressions whose types do not fit together.
+    pub secondary: Vec<Label>,
+    /// Further explanations, printed below the source snippet.
+    pub notes: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    /// Colored with ANSI escape codes.
+    Colored,
+}
+
+const BOLD: &str = "1";
+const RED: &str = "1;31";
+const BLUE: &str = "1;34";
+
+impl Label {
+    pub fn new(span: Span, message: impl Into<String>) -> Label {
+        Label {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn parse_error(err: &ParseError) -> Diagnostic {
+        let (message, label) = match err {
+            ParseError::UnexpectedToken {
+                unexpected, expected, ..
+            } => (
+                format!("unexpected token of type `{unexpected:?}`"),
+                format!("expected {expected}"),
+            ),
+            ParseError::UnexpectedEOF(_) => ("unexpected end of input".into(), "expected more input".into()),
+            ParseError::TrailingTokens(_) => ("extra tokens at end of input".into(), "expected end of input".into()),
+            ParseError::TokenizerError(msg, _) => (format!("tokenization failed: {msg}"), "not a valid token".into()),
+        };
+        Diagnostic {
+            code: err.code(),
+            message,
+            primary: Label::new(err.span(), label),
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Describes an error detected in the expression `e`, given the spans of `e` and the types inferred for its
+    /// sub-expressions while recovering from errors (see `algorithm_j::infer_recovering_typed`).
+    #[allow(nonstandard_style)]
+    pub fn inference_error(err: &LocatedError, e: &Expr, typed: &TypedExpr, spans: &SpanTree) -> Diagnostic {
+        let LocatedError { error, expr } = err;
+        let root = spans;
+        let (typed, spans) = locate(e, typed, spans, expr).expect("errors are detected at sub-expressions");
+
+        let (message, notes) = describe(error);
+
+        let children: Vec<Span> = spans.children.iter().map(|tree| tree.span).collect();
+        let (primary, secondary) = match (typed, error) {
+            (
+                TypedExpr::Var(..),
+                InferenceError::UnknownVar {
+                    name,
+                    bound_elsewhere: true,
+                    ..
+                },
+            ) => {
+                let secondary = binders(e, root, name)
+                    .into_iter()
+                    .map(|span| Label::new(span, format!("`{name}` is only in scope in here")))
+                    .collect();
+                ("not in scope here".to_string(), secondary)
+            }
+            (TypedExpr::Var(..), _) => ("not found in this context".to_string(), Vec::new()),
+            (TypedExpr::App(..), InferenceError::ImpossibleUnification { expected, found, path }) => {
+                // The type of the function is expected to be an arrow from the type of the argument.
+                let secondary = if path.first() == Some(&0) {
+                    vec![
+                        Label::new(
+                            children[0],
+                            format!("but this expects an argument of type `{}`", expected.at(&[0])),
+                        ),
+                        Label::new(children[1], format!("this has type `{}`", found.at(&[0]))),
+                    ]
+                } else {
+                    vec![Label::new(
+                        children[0],
+                        format!("this has type `{expected}`, which is not a function"),
+                    )]
+                };
+                ("in this application".to_string(), secondary)
+            }
+            (TypedExpr::App(e0, e1, _), _) => {
+                let expects = match e0.tau() {
+                    Mono::App(C, taus) if C == ARROW => {
+                        format!("but this expects an argument of type `{}`", taus[0])
+                    }
+                    tau @ Mono::App(..) => format!("this has type `{tau}`, which is not a function"),
+                    tau => format!("this has type `{tau}`"),
+                };
+                let secondary = vec![
+                    Label::new(children[0], expects),
+                    Label::new(children[1], format!("this has type `{}`", e1.tau())),
+                ];
+                ("in this application".to_string(), secondary)
+            }
+            (TypedExpr::Fold(_, e0, tau_rec), _) => (
+                format!("but the annotation expects `{}`", tau_rec.clone().unfold()),
+                vec![Label::new(children[0], format!("this has type `{}`", e0.tau()))],
+            ),
+            (TypedExpr::Unfold(annotation, e0, _), _) => (
+                format!("but the annotation expects an instance of `{annotation}`"),
+                vec![Label::new(children[0], format!("this has type `{}`", e0.tau()))],
+            ),
+            (TypedExpr::Abs(..) | TypedExpr::Let(..) | TypedExpr::Error(..), _) => {
+                ("detected here".to_string(), Vec::new())
+            }
+        };
+
+        Diagnostic {
+            code: error.code(),
+            message,
+            primary: Label::new(spans.span, primary),
+            secondary,
+            notes,
+        }
+    }
+
+    /// Renders the diagnostic, showing every source line a label starts on and underlining the labeled spans: the
+    /// primary span with `^`, and secondary spans with `-`. Notes follow the snippet.
+    pub fn render(&self, source: &str, style: Style) -> String {
+        let paint = |color: &str, text: &str| match style {
+            Style::Plain => text.to_string(),
+            Style::Colored => format!("\x1b[{color}m{text}\x1b[0m"),
+        };
+
+        let mut labels: Vec<(&Label, bool)> = std::iter::once((&self.primary, true))
+            .chain(self.secondary.iter().map(|label| (label, false)))
+            .collect();
+        labels.sort_by_key(|(label, _)| (label.span.start.line, label.span.start.column));
+
+        let lines: Vec<&str> = source.lines().collect();
+        let width = labels
+            .iter()
+            .map(|(label, _)| label.span.start.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+        let bar = paint(BLUE, "|");
+
+        let mut out = String::new();
+        let error = format!("error[{}]", self.code);
+        writeln!(out, "{}: {}", paint(RED, &error), paint(BOLD, &self.message)).unwrap();
+        writeln!(out, "{gutter}{} {}", paint(BLUE, "-->"), self.primary.span.start).unwrap();
+        writeln!(out, "{gutter} {bar}").unwrap();
+
+        let mut previous_line = None;
+        for (label, is_primary) in labels {
+            let Span { start, end } = label.span;
+            let line = lines.get(start.line - 1).copied().unwrap_or("");
+            if previous_line != Some(start.line) {
+                let number = paint(BLUE, &format!("{:>width$}", start.line));
+                writeln!(out, "{number} {bar} {line}").unwrap();
+                previous_line = Some(start.line);
+            }
+
+            let length = if end.line == start.line {
+                end.column - start.column
+            } else {
+                line.chars().count() + 1 - start.column
+            };
+            let (marker, color) = if is_primary { ("^", RED) } else { ("-", BLUE) };
+            let underline = format!("{} {}", marker.repeat(length.max(1)), label.message);
+            let indent = " ".repeat(start.column - 1);
+            writeln!(out, "{gutter} {bar} {indent}{}", paint(color, underline.trim_end())).unwrap();
+        }
+        for note in &self.notes {
+            writeln!(out, "{gutter} {} {}: {note}", paint(BLUE, "="), paint(BOLD, "note")).unwrap();
+        }
+        out
+    }
+}
+
+/// The message and the notes describing an inference error, regardless of where it was detected.
+pub(crate) fn describe(error: &InferenceError) -> (String, Vec<String>) {
+    match error {
+        InferenceError::UnknownVar { name, candidates, .. } => {
+            let candidates: Vec<String> = candidates.iter().map(|y| format!("`{y}`")).collect();
+            let notes = match &candidates[..] {
+                [] => Vec::new(),
+                [y] => vec![format!("did you mean {y}?")],
+                candidates => vec![format!("did you mean one of {}?", candidates.join(", "))],
+            };
+            (format!("unknown variable `{name}`"), notes)
+        }
+        InferenceError::ImpossibleUnification { expected, found, path } => {
+            let notes = if path.is_empty() {
+                Vec::new()
+            } else {
+                vec![format!(
+                    "`{}` and `{}` cannot be unified",
+                    expected.at(path),
+                    found.at(path)
+                )]
+            };
+            (
+                format!("mismatched types: expected `{expected}`, found `{found}`"),
+                notes,
+            )
+        }
+        InferenceError::RecursiveType(tau, alpha) => (
+            format!("unifying `{alpha}` and `{tau}` would create a recursive type"),
+            vec!["no finite type contains itself; the explanation of E0003 shows how to allow recursive types".into()],
+        ),
+    }
+}
+
+/// The typed sub-expression and the span tree of the sub-expression `sub` of `e`, which is identified by its address.
+fn locate<'t, 's>(
+    e: &Expr,
+    typed: &'t TypedExpr,
+    spans: &'s SpanTree,
+    sub: &Expr,
+) -> Option<(&'t TypedExpr, &'s SpanTree)> {
+    if std::ptr::eq(e, sub) {
+        return Some((typed, spans));
+    }
+    e.children()
+        .into_iter()
+        .zip(typed.children())
+        .zip(&spans.children)
+        .find_map(|((e, typed), spans)| locate(e, typed, spans, sub))
+}
+
+/// The spans of the λs and `let`s in `e` that bind the variable `x`.
+fn binders(e: &Expr, spans: &SpanTree, x: &str) -> Vec<Span> {
+    let mut binders: Vec<Span> = e
+        .children()
+        .into_iter()
+        .zip(&spans.children)
+        .flat_map(|(e, spans)| binders(e, spans, x))
+        .collect();
+    if let Expr::Abs(y, _) | Expr::Let(y, ..) = e
+        && x == y
+    {
+        binders.insert(0, spans.span);
+    }
+    binders
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::infer_recovering_typed,
+        ctxt::Ctxt,
+        parse::{parse_poly, parse_spanned},
+    };
+
+    use super::*;
+
+    #[allow(nonstandard_style)]
+    fn render_inference_errors(text: &str, Gamma: &Ctxt) -> String {
+        let (e, spans) = parse_spanned(text).unwrap();
+        let (_, typed, errors) = infer_recovering_typed(&e, Gamma);
+        errors
+            .iter()
+            .map(|err| Diagnostic::inference_error(err, &e, &typed, &spans).render(text, Style::Plain))
+            .collect()
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_render_impossible_unification() {
+        let Gamma = Ctxt::new()
+            .bind("unify", parse_poly("∀ x . x → x → x").unwrap())
+            .bind("n", parse_poly("Int").unwrap())
+            .bind("s", parse_poly("String").unwrap());
+
+        assert_eq!(
+            render_inference_errors("λ y . unify n s", &Gamma),
+            "\
+error[E0002]: mismatched types: expected `Int → Int`, found `String → _4`
+ --> 1:7
+  |
+1 | λ y . unify n s
+  |       ^^^^^^^^^ in this application
+  |       ------- but this expects an argument of type `Int`
+  |               - this has type `String`
+  = note: `Int` and `String` cannot be unified
+"
+        );
+    }
+
+    #[test]
+    fn test_render_multiple_lines() {
+        let text = "let f = λ x . x\nin f g";
+
+        assert_eq!(
+            render_inference_errors(text, &Ctxt::new()),
+            "\
+error[E0001]: unknown variable `g`
+ --> 2:6
+  |
+2 | in f g
+  |      ^ not found in this context
+"
+        );
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_render_unknown_var() {
+        let Gamma = Ctxt::new().bind("length", parse_poly("Int").unwrap());
+
+        assert_eq!(
+            render_inference_errors("(λ xs . xs) (lenght xs)", &Gamma),
+            "\
+error[E0001]: unknown variable `lenght`
+ --> 1:14
+  |
+1 | (λ xs . xs) (lenght xs)
+  |              ^^^^^^ not found in this context
+  = note: did you mean `length`?
+error[E0001]: unknown variable `xs`
+ --> 1:21
+  |
+1 | (λ xs . xs) (lenght xs)
+  |  --------- `xs` is only in scope in here
+  |                     ^^ not in scope here
+"
+        );
+    }
+
+    #[test]
+    fn test_render_parse_error() {
+        let text = "let x in y";
+        let err = parse_spanned(text).unwrap_err();
+
+        assert_eq!(
+            Diagnostic::parse_error(&err).render(text, Style::Plain),
+            "\
+error[E0101]: unexpected token of type `In`
+ --> 1:7
+  |
+1 | let x in y
+  |       ^^ expected '='
+"
+        );
+    }
+
+    #[test]
+    fn test_render_colored() {
+        let text = "x )";
+        let err = parse_spanned(text).unwrap_err();
+
+        let rendered = Diagnostic::parse_error(&err).render(text, Style::Colored);
+
+        assert!(rendered.starts_with("\x1b[1;31merror[E0103]\x1b[0m: \x1b[1mextra tokens at end of input\x1b[0m\n"));
+    }
+}
@@ -0,0 +1,169 @@
+/// The long-form explanations of the error codes of `ParseError::code` and `InferenceError::code`.
+const EXPLANATIONS: [(&str, &str); 7] = [
+    (
+        "E0001",
+        "\
+A variable is used that is not bound.
+
+Erroneous example:
+
+    λ f . f y
+
+Every variable must be bound by an enclosing `λ` or `let`, or be in the context, which holds the definitions
+entered so far (`def x = e`). Here, `y` is bound nowhere. This is often a typo, in which case the error suggests the
+variables in scope with similar names.
+
+A variable bound in one branch of an expression is not in scope in another one:
+
+    (λ x . x) x
+
+The `λ` only binds `x` in its body `x`, not in the argument to the right. To use the same value in both places,
+bind it with `let` around both, e.g., `let x = λ y . y in (λ z . z) x`.",
+    ),
+    (
+        "E0002",
+        "\
+Two types that must be equal have different type constructors.
+
+Erroneous example, with `n : Int` and `s : String` in the context, and `unify : ∀ x . x → x → x`:
+
+    unify n s
+
+After `unify n`, the type variable `x` is `Int`, so the function expects an argument of type `Int`, but `s` has
+type `String`. The error shows the whole type that was expected, here `Int → Int`, and the whole type that was found,
+here `String → _4`, and notes which parts of them clash.
+
+Recursive types `μ t . τ` are nominal under `fold` and `unfold`: they are only equal if their bodies are, so
+
+    λ x . unfold [μ t . t → a] (fold [μ s . s → s] x)
+
+fails, since `μ s . s → s` would require `a` to be the recursive type itself, which the annotation does not state.",
+    ),
+    (
+        "E0003",
+        "\
+Unifying a type variable with a type containing that variable would create an infinite type.
+
+Erroneous example:
+
+    λ x . x x
+
+To apply `x` to itself, `x` must have a function type `_1 → _2` whose argument type `_1` is the type of `x`
+itself, i.e., `_1 = _1 → _2`. No finite type solves this equation, so the occurs check rejects it, and with it
+self-application and fixed-point combinators such as `λ f . (λ x . f (x x)) (λ x . f (x x))`.
+
+If an infinite type is really intended, there are two ways out. Prefixing the expression with `:equirecursive`
+allows type variables to become recursive types `μ a . τ`, so that `λ x . x x` gets the type
+`∀ a . (μ b . b → a) → a`. Alternatively, state where the recursive type is needed with an explicit `unfold`:
+
+    λ x . (unfold [μ t . t → a] x) x",
+    ),
+    (
+        "E0101",
+        "\
+The parser encountered a token it does not expect at this position.
+
+Erroneous example:
+
+    let x in x
+
+A `let` must bind its variable with `=`, as in `let x = λ y . y in x`. The error names the tokens that would have
+been valid instead.",
+    ),
+    (
+        "E0102",
+        "\
+The input ended in the middle of an expression or a type.
+
+Erroneous example:
+
+    (λ x . x
+
+Every `(` needs a matching `)`, every `λ x .` a body, and every `let x = e in` a body after `in`.",
+    ),
+    (
+        "E0103",
+        "\
+The input continues after a complete expression or type.
+
+Erroneous example:
+
+    λ x . x )
+
+Here, `λ x . x` is complete, and the parenthesis after it has no matching `(`. Note that application extends as far
+to the right as possible, so `f x y` is a single expression, but a stray `)` or a second `.` is not.",
+    ),
+    (
+        "E0104",
+        "\
+The input contains a character that is not part of any token, or a reserved identifier.
+
+Erroneous example:
+
+    λ x . x $
+
+Identifiers consist of Unicode letters other than `λ` and `μ`, digits, and underscores, starting with a letter or an
+underscore, and may end in primes `'`. Identifiers of the form `_1`, `_2`, ... are reserved for the type variables
+introduced by inference. Besides identifiers, the tokens are `λ`, `.`, `=`, `(`, `)`, `∀`, `→`, `μ`, `[`, `]`, and
+`;`, with the ASCII alternatives `lambda`, `forall`, `to`, and `mu`.
+
+Comments are skipped like whitespace: `--` starts a comment up to the end of the line, and `{-` one up to the
+matching `-}`, which may contain nested block comments. A block comment that is never closed is reported at its `{-`,
+and a lone `-` or `{` is not a token.",
+    ),
+];
+
+/// The long-form explanation of the error code, with examples.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(other, _)| code.eq_ignore_ascii_case(other))
+        .map(|(_, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::{infer, infer_recovering},
+        ctxt::Ctxt,
+        parse::parse,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_every_code_is_explained() {
+        let errors = [
+            infer(&parse("λ f . f y").unwrap(), &Ctxt::new()).unwrap_err(),
+            infer(
+                &parse("λ x . unfold [μ t . t → a] (fold [μ s . s → s] x)").unwrap(),
+                &Ctxt::new(),
+            )
+            .unwrap_err(),
+            infer(&parse("λ x . x x").unwrap(), &Ctxt::new()).unwrap_err(),
+        ];
+        let parse_errors = ["let x in x", "(λ x . x", "λ x . x )", "λ x . x $"].map(|text| parse(text).unwrap_err());
+
+        let codes: Vec<&str> = errors
+            .iter()
+            .map(|err| err.code())
+            .chain(parse_errors.iter().map(|err| err.code()))
+            .collect();
+
+        assert_eq!(codes, ["E0001", "E0002", "E0003", "E0101", "E0102", "E0103", "E0104"]);
+        for code in codes {
+            assert!(explain(code).is_some());
+        }
+        assert_eq!(explain("e0003"), explain("E0003"));
+        assert_eq!(explain("E9999"), None);
+    }
+
+    #[test]
+    fn test_examples_fail_as_explained() {
+        let (_, errors) = infer_recovering(&parse("(λ x . x) x").unwrap(), &Ctxt::new());
+
+        assert_eq!(errors[0].code(), "E0001");
+        assert!(infer(&parse("let x = λ y . y in (λ z . z) x").unwrap(), &Ctxt::new()).is_ok());
+        assert!(infer(&parse("λ x . (unfold [μ t . t → a] x) x").unwrap(), &Ctxt::new()).is_ok());
+    }
+}
@@ -1,11 +1,22 @@
+use std::collections::HashSet;
+
+use crate::types::Mono;
+
 pub type Var = String;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Expr {
     Var(Var),
     App(Box<Expr>, Box<Expr>),
     Abs(Var, Box<Expr>),
     Let(Var, Box<Expr>, Box<Expr>),
+    /// `fold [μα . τ] e`, which turns `e : τ[α := μα . τ]` into a value of the annotated recursive type.
+    Fold(Mono, Box<Expr>),
+    /// `unfold [μα . τ] e`, which turns `e : μα . τ` into a value of type `τ[α := μα . τ]`.
+    Unfold(Mono, Box<Expr>),
+    /// A syntax error the parser recovered from (see `parse::parse_recovering`), printed as `?`. Inference skips it
+    /// by giving it a fresh type.
+    Error,
 }
 
 impl Expr {
@@ -25,9 +36,55 @@ impl Expr {
         Expr::Let(x.into(), Box::new(e1), Box::new(e2))
     }
 
+    /// The variables occurring free in the expression.
+    pub fn free(&self) -> HashSet<Var> {
+        match self {
+            Expr::Var(x) => HashSet::from([x.clone()]),
+            Expr::Error => HashSet::new(),
+            Expr::App(e1, e2) => &e1.free() | &e2.free(),
+            Expr::Abs(x, e) => {
+                let mut free = e.free();
+                free.remove(x);
+                free
+            }
+            Expr::Let(x, e1, e2) => {
+                let mut free = e2.free();
+                free.remove(x);
+                &e1.free() | &free
+            }
+            Expr::Fold(_, e) | Expr::Unfold(_, e) => e.free(),
+        }
+    }
+
+    pub fn fold(tau: Mono, e: Expr) -> Expr {
+        Expr::Fold(tau, Box::new(e))
+    }
+
+    pub fn unfold(tau: Mono, e: Expr) -> Expr {
+        Expr::Unfold(tau, Box::new(e))
+    }
+
+    /// The variables bound somewhere in the expression, by a λ or a `let`.
+    pub fn bound(&self) -> HashSet<Var> {
+        let mut bound: HashSet<Var> = self.children().into_iter().flat_map(Expr::bound).collect();
+        if let Expr::Abs(x, _) | Expr::Let(x, ..) = self {
+            bound.insert(x.clone());
+        }
+        bound
+    }
+
+    /// The immediate sub-expressions, from left to right.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Var(..) | Expr::Error => Vec::new(),
+            Expr::App(e1, e2) | Expr::Let(_, e1, e2) => vec![e1, e2],
+            Expr::Abs(_, e) | Expr::Fold(_, e) | Expr::Unfold(_, e) => vec![e],
+        }
+    }
+
     pub fn is_closed(&self) -> bool {
         match self {
-            Expr::Var(..) | Expr::App(..) => true,
+            Expr::Var(..) | Expr::App(..) | Expr::Fold(..) | Expr::Unfold(..) | Expr::Error => true,
             Expr::Abs(..) | Expr::Let(..) => false,
         }
     }
@@ -35,6 +92,11 @@ impl Expr {
     pub fn is_var(&self) -> bool {
         matches!(self, Self::Var(..))
     }
+
+    /// Whether the expression contains a syntax error.
+    pub fn has_errors(&self) -> bool {
+        matches!(self, Expr::Error) || self.children().into_iter().any(Expr::has_errors)
+    }
 }
 
 impl std::fmt::Display for Expr {
@@ -50,14 +112,27 @@ impl std::fmt::Display for Expr {
 
                 write!(f, " ")?;
 
-                if e2.is_var() {
+                if e2.is_var() || matches!(**e2, Expr::Error) {
                     write!(f, "{}", e2)
                 } else {
                     write!(f, "({})", e2)
                 }
             }
             Expr::Abs(x, e) => write!(f, "λ{} . {}", x, e),
+            Expr::Error => write!(f, "?"),
             Expr::Let(x, e1, e2) => write!(f, "let {} = {} in {}", x, e1, e2),
+            Expr::Fold(tau, e) | Expr::Unfold(tau, e) => {
+                let keyword = if matches!(self, Expr::Fold(..)) {
+                    "fold"
+                } else {
+                    "unfold"
+                };
+                if e.is_var() {
+                    write!(f, "{} [{}] {}", keyword, tau, e)
+                } else {
+                    write!(f, "{} [{}] ({})", keyword, tau, e)
+                }
+            }
         }
     }
 }
@@ -0,0 +1,241 @@
+use crate::{
+    algorithm_j::{InferenceError, LocatedError},
+    diagnostic::{self, Diagnostic, Label},
+    expr::Expr,
+    parse::ParseError,
+    span::{Position, Span, SpanTree},
+    typed_expr::TypedExpr,
+    types::{Mono, Poly},
+};
+
+/// A JSON value. Objects keep their fields in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(usize),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<'k>(fields: impl IntoIterator<Item = (&'k str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.into(), value)).collect())
+    }
+
+    pub fn array<T>(items: impl IntoIterator<Item = T>, to_json: impl Fn(T) -> Json) -> Json {
+        Json::Array(items.into_iter().map(to_json).collect())
+    }
+}
+
+impl From<&str> for Json {
+    fn from(text: &str) -> Json {
+        Json::String(text.into())
+    }
+}
+
+impl From<String> for Json {
+    fn from(text: String) -> Json {
+        Json::String(text)
+    }
+}
+
+/// A parse error as a diagnostic record (see [`diagnostic`]).
+pub fn parse_error(err: &ParseError) -> Json {
+    record(&Diagnostic::parse_error(err), Json::Object(Vec::new()))
+}
+
+/// An inference error without a location, as a diagnostic record without spans.
+pub fn inference_error(err: &InferenceError) -> Json {
+    let (message, notes) = diagnostic::describe(err);
+    Json::object([
+        ("severity", "error".into()),
+        ("code", err.code().into()),
+        ("message", message.into()),
+        ("spans", Json::Array(Vec::new())),
+        ("notes", Json::array(notes, Json::from)),
+        ("types", types(err)),
+    ])
+}
+
+/// An inference error detected in the expression `e` as a diagnostic record, with the same arguments as
+/// [`Diagnostic::inference_error`].
+pub fn located_inference_error(err: &LocatedError, e: &Expr, typed: &TypedExpr, spans: &SpanTree) -> Json {
+    let diagnostic = Diagnostic::inference_error(err, e, typed, spans);
+    record(&diagnostic, types(&err.error))
+}
+
+/// A diagnostic record: the severity, the code, the message, the labeled spans, the notes, and the types involved,
+/// keyed by their role.
+fn record(diagnostic: &Diagnostic, types: Json) -> Json {
+    let labels = std::iter::once((&diagnostic.primary, true)).chain(diagnostic.secondary.iter().map(|l| (l, false)));
+    Json::object([
+        ("severity", "error".into()),
+        ("code", diagnostic.code.into()),
+        ("message", diagnostic.message.as_str().into()),
+        (
+            "spans",
+            Json::array(labels, |(label, primary)| self::label(label, primary)),
+        ),
+        ("notes", Json::array(&diagnostic.notes, |note| note.as_str().into())),
+        ("types", types),
+    ])
+}
+
+fn types(err: &InferenceError) -> Json {
+    match err {
+        InferenceError::UnknownVar { .. } => Json::Object(Vec::new()),
+        InferenceError::ImpossibleUnification { expected, found, .. } => {
+            Json::object([("expected", mono(expected)), ("found", mono(found))])
+        }
+        InferenceError::RecursiveType(tau, alpha) => {
+            Json::object([("variable", mono(&Mono::Var(alpha.clone()))), ("type", mono(tau))])
+        }
+    }
+}
+
+fn label(label: &Label, primary: bool) -> Json {
+    let Span { start, end } = label.span;
+    Json::object([
+        ("start", position(start)),
+        ("end", position(end)),
+        ("primary", Json::Bool(primary)),
+        ("label", label.message.as_str().into()),
+    ])
+}
+
+fn position(position: Position) -> Json {
+    Json::object([
+        ("offset", Json::Number(position.offset)),
+        ("line", Json::Number(position.line)),
+        ("column", Json::Number(position.column)),
+    ])
+}
+
+/// A type, both as text and as a tree.
+pub fn mono(tau: &Mono) -> Json {
+    Json::object([("text", tau.to_string().into()), ("tree", mono_tree(tau))])
+}
+
+/// A type scheme, as text, as its bound variables in order, and as the tree of its type.
+pub fn poly(sigma: &Poly) -> Json {
+    let Poly(_, tau) = sigma;
+    Json::object([
+        ("text", sigma.to_string().into()),
+        ("vars", Json::array(sigma.bound_vars(), |alpha| alpha.as_str().into())),
+        ("tree", mono_tree(tau)),
+    ])
+}
+
+#[allow(nonstandard_style)]
+fn mono_tree(tau: &Mono) -> Json {
+    match tau {
+        Mono::Var(alpha) => Json::object([("kind", "var".into()), ("name", alpha.as_str().into())]),
+        Mono::App(C, taus) => Json::object([
+            ("kind", "app".into()),
+            ("constructor", C.as_str().into()),
+            ("args", Json::array(taus, mono_tree)),
+        ]),
+        Mono::Rec(alpha, tau) => Json::object([
+            ("kind", "rec".into()),
+            ("var", alpha.as_str().into()),
+            ("body", mono_tree(tau)),
+        ]),
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(text) => write_string(f, text),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, text: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::infer_recovering_typed,
+        ctxt::Ctxt,
+        parse::{parse_poly, parse_spanned},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_escape_strings() {
+        let json = Json::object([("a\"b", "c\\d\n\u{1}".into()), ("e", Json::Array(vec![Json::Null]))]);
+
+        assert_eq!(json.to_string(), r#"{"a\"b":"c\\d\n\u0001","e":[null]}"#);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = parse_spanned("λ x").unwrap_err();
+
+        assert_eq!(
+            parse_error(&err).to_string(),
+            r#"{"severity":"error","code":"E0102","message":"unexpected end of input","spans":[{"start":{"offset":4,"line":1,"column":4},"end":{"offset":4,"line":1,"column":4},"primary":true,"label":"expected more input"}],"notes":[],"types":{}}"#
+        );
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_located_inference_error() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+        let (e, spans) = parse_spanned("n n").unwrap();
+        let (_, typed, errors) = infer_recovering_typed(&e, &Gamma);
+
+        let Json::Object(fields) = located_inference_error(&errors[0], &e, &typed, &spans) else {
+            panic!()
+        };
+
+        assert_eq!(fields[1], ("code".into(), "E0002".into()));
+        let (_, types) = &fields[5];
+        assert_eq!(
+            types.to_string(),
+            r#"{"expected":{"text":"Int","tree":{"kind":"app","constructor":"Int","args":[]}},"found":{"text":"Int → _1","tree":{"kind":"app","constructor":"→","args":[{"kind":"app","constructor":"Int","args":[]},{"kind":"var","name":"_1"}]}}}"#
+        );
+    }
+}
@@ -0,0 +1,16 @@
+pub mod algorithm_j;
+pub mod algorithm_m;
+pub mod algorithm_w;
+pub mod constraints;
+pub mod ctxt;
+pub mod derivation;
+pub mod diagnostic;
+pub mod explain;
+pub mod expr;
+pub mod json;
+pub mod parse;
+pub mod program;
+pub mod span;
+pub mod system_f;
+pub mod typed_expr;
+pub mod types;
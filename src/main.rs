@@ -1,21 +1,59 @@
-mod algorithm_j;
-mod ctxt;
-mod expr;
-mod parse;
-mod types;
-
-use std::io::{Write, stdin, stdout};
+use std::{
+    collections::HashMap,
+    io::{IsTerminal, Write, stdin, stdout},
+    rc::Rc,
+};
 
-use crate::{
-    algorithm_j::{InferenceError, infer},
+use hindley_milner::{
+    algorithm_j::{self, LocatedError, infer_recovering_typed, infer_traced},
+    algorithm_m, algorithm_w,
+    constraints::{self, SequentialSolver, Solver},
     ctxt::Ctxt,
-    parse::{ParseError, parse},
+    derivation::{self, Derivation},
+    diagnostic::{Diagnostic, Style},
+    explain::explain,
+    expr::{Expr, Var},
+    json::{self, Json},
+    parse::{ParseError, parse_program_recovering, parse_recovering},
+    program::{Definition, Program, Session},
+    span::SpanTree,
+    system_f,
+    types::Poly,
 };
 
+/// The text each definition of the program was parsed from, together with the spans of its expression in it.
+type Sources = HashMap<Var, (Rc<str>, SpanTree)>;
+
+type Algorithm = for<'a> fn(&'a Expr, &Ctxt) -> Result<Poly, LocatedError<'a>>;
+
+const ALGORITHMS: [(&str, Algorithm); 3] = [
+    ("J", algorithm_j::infer_located),
+    ("W", algorithm_w::infer_located),
+    ("M", algorithm_m::infer_located),
+];
+
+#[allow(nonstandard_style)]
 fn main() {
+    let mut program = Program::default();
+    let mut session = Session::new(Ctxt::new());
+    let mut sources = Sources::new();
+    let mut Gamma = Ctxt::new();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--explain") {
+        let code = args.get(i + 1).map_or("", String::as_str);
+        if !try_explain(code) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    // Prints the result of every input as a single line of JSON instead, for use by other tools.
+    let json = args.iter().any(|arg| arg == "--json");
+
     loop {
-        print!(">>> ");
-        stdout().flush().unwrap();
+        if !json {
+            print!(">>> ");
+            stdout().flush().unwrap();
+        }
 
         let mut text = String::new();
         stdin().read_line(&mut text).unwrap();
@@ -23,35 +61,323 @@ fn main() {
         if text.trim().is_empty() {
             break;
         }
+        let text = text.trim_end();
 
-        try_infer(&text);
+        if text.split_whitespace().next() == Some("def") {
+            let define = if json { try_define_json } else { try_define };
+            if let Some(Gamma_prime) = define(text, &mut program, &mut session, &mut sources) {
+                Gamma = Gamma_prime;
+            }
+        } else if json {
+            // Of the commands, only `:explain` has a JSON form.
+            match command(text, ":explain") {
+                Some(code) => try_explain_json(text, code.trim()),
+                None if text.trim_start().starts_with(':') => {
+                    let error = "only expressions, definitions, and ':explain' are supported with '--json'";
+                    print_json(text, [("error", error.into())]);
+                }
+                None => try_infer_json(text, &Gamma),
+            }
+        } else if let Some(code) = command(text, ":explain") {
+            try_explain(code.trim());
+        } else if let Some(text) = command(text, ":compare") {
+            try_compare(text, &Gamma);
+        } else if let Some(text) = command(text, ":equirecursive") {
+            try_infer_equirecursive(text, &Gamma);
+        } else if let Some(text) = command(text, ":typed") {
+            try_infer_typed(text, &Gamma);
+        } else if let Some(text) = command(text, ":systemf") {
+            try_translate(text, &Gamma);
+        } else if let Some(text) = command(text, ":constraints") {
+            try_generate_constraints(text, &Gamma);
+        } else if let Some(text) = command(text, ":trace") {
+            try_trace(text, &Gamma);
+        } else if let Some(text) = command(text, ":tree") {
+            try_derive(text, &Gamma, Derivation::to_tree);
+        } else if let Some(text) = command(text, ":latex") {
+            try_derive(text, &Gamma, Derivation::to_latex);
+        } else {
+            try_infer(text, &Gamma);
+        }
     }
 }
 
+/// The text after the command, if the line starts with it. The expression after the command starts at the first
+/// character that is not whitespace, so that spans are counted from there.
+fn command<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.trim_start().strip_prefix(name).map(str::trim_start)
+}
+
+/// Adds the definitions to the program, or replaces those of the same name, and prints the types of all definitions
+/// whose types had to be inferred again. Returns the context extended by the definitions.
 #[allow(nonstandard_style)]
-fn try_infer(text: &str) {
-    let Gamma = Ctxt::new();
+fn try_define(text: &str, program: &mut Program, session: &mut Session, sources: &mut Sources) -> Option<Ctxt> {
+    let (Program { definitions }, spans, errors) = parse_program_recovering(text);
+    if !errors.is_empty() {
+        for err in errors {
+            print_parse_error(&err, text);
+        }
+        return None;
+    }
+
+    add_definitions(text, definitions, spans, program, sources);
 
-    match parse(text) {
-        Err(ParseError::UnexpectedToken { unexpected, expected }) => {
-            println!("Unexpected token of type {unexpected:?}, expected {expected} instead.")
+    let (typings, Gamma) = session.infer(program);
+    for typing in typings.into_iter().filter(|typing| !typing.cached) {
+        match (typing.result, typing.failure) {
+            (Ok(sigma), _) => println!("{} : {}", typing.name, sigma.normalize()),
+            (Err(_), failure) => {
+                let (definition, typed, errors) =
+                    failure.expect("failed typings that are not cached carry their errors");
+                let (source, spans) = &sources[&definition.name];
+                for err in errors {
+                    let diagnostic = Diagnostic::inference_error(&err, &definition.expr, &typed, spans);
+                    print!("{}", diagnostic.render(source, style()));
+                }
+            }
         }
-        Err(ParseError::UnexpectedEOF) => println!("Parsing failed: Unexpectedly reached end of file."),
-        Err(ParseError::TrailingTokens) => println!("Parsing failed: Extra tokens at end of input."),
-        Err(ParseError::TokenizerError(msg)) => println!("Parsing failed: Tokenization failed: '{msg}'."),
+    }
+    Some(Gamma)
+}
+
+/// Adds the definitions parsed from the text to the program, and remembers where they were parsed from.
+fn add_definitions(
+    text: &str,
+    definitions: Vec<Definition>,
+    spans: Vec<SpanTree>,
+    program: &mut Program,
+    sources: &mut Sources,
+) {
+    let source: Rc<str> = text.into();
+    for (definition, spans) in definitions.into_iter().zip(spans) {
+        sources.insert(definition.name.clone(), (source.clone(), spans));
+        program.define(definition);
+    }
+}
 
-        Ok(e) => match infer(&e, &Gamma) {
-            Err(InferenceError::UnknownVar(x)) => {
-                println!("Type inference failed: Encountered unknown variable during inference: '{x}'.")
+/// Like [`try_define`], but prints the types or errors of the definitions as JSON.
+#[allow(nonstandard_style)]
+fn try_define_json(text: &str, program: &mut Program, session: &mut Session, sources: &mut Sources) -> Option<Ctxt> {
+    let (Program { definitions }, spans, errors) = parse_program_recovering(text);
+    if !errors.is_empty() {
+        print_json(text, [("diagnostics", Json::array(&errors, json::parse_error))]);
+        return None;
+    }
+
+    add_definitions(text, definitions, spans, program, sources);
+
+    let (typings, Gamma) = session.infer(program);
+    let typings = typings.into_iter().filter(|typing| !typing.cached).map(|typing| {
+        let (sigma, diagnostics) = match (typing.result, typing.failure) {
+            (Ok(sigma), _) => (json::poly(&sigma.normalize()), Vec::new()),
+            (Err(_), failure) => {
+                let (definition, typed, errors) =
+                    failure.expect("failed typings that are not cached carry their errors");
+                let (_, spans) = &sources[&definition.name];
+                let diagnostics = errors
+                    .iter()
+                    .map(|err| json::located_inference_error(err, &definition.expr, &typed, spans));
+                (Json::Null, diagnostics.collect())
             }
-            Err(InferenceError::ImpossibleUnification(tau1, tau2)) => {
-                println!("Type inference failed: Cannot unify types '{tau1}' and '{tau2}'.")
+        };
+        Json::object([
+            ("name", typing.name.into()),
+            ("type", sigma),
+            ("diagnostics", Json::Array(diagnostics)),
+        ])
+    });
+    print_json(text, [("definitions", Json::Array(typings.collect()))]);
+    Some(Gamma)
+}
+
+/// Like [`try_infer`], but prints the type or the errors as JSON.
+#[allow(nonstandard_style)]
+fn try_infer_json(text: &str, Gamma: &Ctxt) {
+    let (e, spans, parse_errors) = parse_recovering(text);
+
+    let (sigma, typed, errors) = infer_recovering_typed(&e, Gamma);
+    if parse_errors.is_empty() && errors.is_empty() {
+        let sigma = json::poly(&sigma.normalize());
+        return print_json(text, [("type", sigma), ("diagnostics", Json::Array(Vec::new()))]);
+    }
+
+    let diagnostics = parse_errors.iter().map(json::parse_error).chain(
+        errors
+            .iter()
+            .map(|err| json::located_inference_error(err, &e, &typed, &spans)),
+    );
+    print_json(
+        text,
+        [
+            ("type", Json::Null),
+            ("diagnostics", Json::Array(diagnostics.collect())),
+        ],
+    );
+}
+
+/// Prints the fields, preceded by the input they belong to, as a JSON object on a single line.
+fn print_json<'k>(text: &str, fields: impl IntoIterator<Item = (&'k str, Json)>) {
+    println!(
+        "{}",
+        Json::object(std::iter::once(("input", text.into())).chain(fields))
+    );
+}
+
+/// Prints the type of the expression, or all syntax errors and all type errors of the rest of the expression.
+#[allow(nonstandard_style)]
+fn try_infer(text: &str, Gamma: &Ctxt) {
+    let (e, spans, parse_errors) = parse_recovering(text);
+    for err in &parse_errors {
+        print_parse_error(err, text);
+    }
+
+    let (sigma, typed, errors) = infer_recovering_typed(&e, Gamma);
+    if errors.is_empty() {
+        if parse_errors.is_empty() {
+            println!("⊢ {e} : {}", sigma.normalize());
+        }
+        return;
+    }
+
+    for err in errors {
+        print!(
+            "{}",
+            Diagnostic::inference_error(&err, &e, &typed, &spans).render(text, style())
+        );
+    }
+}
+
+#[allow(nonstandard_style)]
+fn try_infer_equirecursive(text: &str, Gamma: &Ctxt) {
+    let Some((e, _)) = try_parse(text) else { return };
+
+    match algorithm_j::infer_equirecursive(&e, Gamma) {
+        Err(err) => println!("Type inference failed: {err}."),
+        Ok(sigma) => println!("⊢ {e} : {}", sigma.normalize()),
+    }
+}
+
+#[allow(nonstandard_style)]
+fn try_infer_typed(text: &str, Gamma: &Ctxt) {
+    let Some((e, _)) = try_parse(text) else { return };
+
+    match algorithm_j::infer_typed(&e, Gamma) {
+        Err(err) => println!("Type inference failed: {err}."),
+        Ok(e) => println!("⊢ {e}"),
+    }
+}
+
+#[allow(nonstandard_style)]
+fn try_translate(text: &str, Gamma: &Ctxt) {
+    let Some((e, _)) = try_parse(text) else { return };
+
+    let e = match system_f::translate(&e, Gamma) {
+        Err(err) => return println!("Type inference failed: {err}."),
+        Ok(e) => e,
+    };
+
+    match system_f::check(&e, Gamma) {
+        Err(err) => println!("Type checking the translation '{e}' failed: {err}."),
+        Ok(tau) => println!("⊢ {e} : {tau}"),
+    }
+}
+
+#[allow(nonstandard_style)]
+fn try_compare(text: &str, Gamma: &Ctxt) {
+    let Some((e, spans)) = try_parse(text) else { return };
+
+    for (name, infer) in ALGORITHMS {
+        match infer(&e, Gamma) {
+            Err(LocatedError { error, expr }) => {
+                let span = spans.find(&e, expr).expect("errors are detected at sub-expressions");
+                println!("{name}: {error}, detected at '{expr}' ({span}).")
             }
-            Err(InferenceError::RecursiveType(tau, alpha)) => {
-                println!("Type inference failed: Unifying '{tau}' and '{alpha}' would create recursive type.")
+            Ok(sigma) => println!("{name}: ⊢ {e} : {}", sigma.normalize()),
+        }
+    }
+}
+
+#[allow(nonstandard_style)]
+fn try_generate_constraints(text: &str, Gamma: &Ctxt) {
+    let Some((e, _)) = try_parse(text) else { return };
+
+    let constraints = match constraints::generate(&e, Gamma) {
+        Err(err) => return println!("Constraint generation failed: {err}."),
+        Ok(constraints) => constraints,
+    };
+
+    println!("{constraints}⊢ {e} : {}", constraints.tau);
+
+    if let Err(err) = SequentialSolver::new().solve(&constraints) {
+        println!("Solving failed: {err}.");
+        if let Some(core) = constraints::unsatisfiable_core(&constraints) {
+            println!("The constraints of the following sub-expressions are jointly unsatisfiable:");
+            for e in core {
+                println!("  {e}");
             }
+        }
+    }
+}
+
+#[allow(nonstandard_style)]
+fn try_trace(text: &str, Gamma: &Ctxt) {
+    let Some((e, spans)) = try_parse(text) else { return };
+
+    let (result, trace) = infer_traced(&e, Gamma);
+    print!("{trace}");
+    match result {
+        Err(LocatedError { error, expr }) => {
+            let span = spans.find(&e, expr).expect("errors are detected at sub-expressions");
+            println!("Type inference failed: {error}, detected at '{expr}' ({span}).")
+        }
+        Ok(sigma) => println!("⊢ {e} : {sigma}"),
+    }
+}
+
+#[allow(nonstandard_style)]
+fn try_derive(text: &str, Gamma: &Ctxt, render: fn(&Derivation) -> String) {
+    let Some((e, _)) = try_parse(text) else { return };
+
+    match derivation::derive(&e, Gamma) {
+        Err(err) => println!("Type inference failed: {err}."),
+        Ok(derivation) => println!("{}", render(&derivation).trim_end()),
+    }
+}
+
+/// Prints the explanation of the error code, and returns whether there is one.
+fn try_explain(code: &str) -> bool {
+    let explanation = explain(code);
+    match explanation {
+        Some(explanation) => println!("{explanation}"),
+        None => println!("Unknown error code '{code}'; the codes are shown in brackets after 'error'."),
+    }
+    explanation.is_some()
+}
+
+/// Like [`try_explain`], but prints the explanation, or null for an unknown code, as JSON.
+fn try_explain_json(text: &str, code: &str) {
+    let explanation = explain(code).map_or(Json::Null, Json::from);
+    print_json(text, [("code", code.into()), ("explanation", explanation)]);
+}
+
+/// Parses the expression, or prints all syntax errors in it.
+fn try_parse(text: &str) -> Option<(Expr, SpanTree)> {
+    let (e, spans, errors) = parse_recovering(text);
+    for err in &errors {
+        print_parse_error(err, text);
+    }
+    errors.is_empty().then_some((e, spans))
+}
+
+fn print_parse_error(err: &ParseError, text: &str) {
+    print!("{}", Diagnostic::parse_error(err).render(text, style()));
+}
 
-            Ok(sigma) => println!("⊢ {e} : {sigma}"),
-        },
+/// Colors diagnostics when printing to a terminal, unless disabled by setting `NO_COLOR`.
+fn style() -> Style {
+    if stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        Style::Colored
+    } else {
+        Style::Plain
     }
 }
@@ -2,153 +2,446 @@ mod token;
 #[macro_use]
 mod macros;
 
-use std::iter::Peekable;
+use crate::{
+    expr::Expr,
+    parse::token::Token,
+    program::{Definition, Program},
+    span::{Position, Span, SpanTree},
+    types::{Mono, Poly, TypeVar},
+};
 
-use crate::{expr::Expr, parse::token::Token};
-
-#[cfg(test)]
-use crate::types::{Mono, Poly, TypeVar};
-
-#[cfg(test)]
 use std::collections::HashSet;
 
+/// An error in the text, together with the span it was detected at. At the end of the text, this is the empty span
+/// after the last character.
 #[derive(Debug, PartialEq, Eq)]
 pub enum ParseError {
-    UnexpectedToken { unexpected: Token, expected: String },
-    UnexpectedEOF,
-    TrailingTokens,
-    TokenizerError(String),
+    UnexpectedToken {
+        unexpected: Token,
+        expected: String,
+        span: Span,
+    },
+    UnexpectedEOF(Span),
+    TrailingTokens(Span),
+    TokenizerError(String, Span),
 }
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEOF(span)
+            | ParseError::TrailingTokens(span)
+            | ParseError::TokenizerError(_, span) => *span,
+        }
+    }
+
+    /// A stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "E0101",
+            ParseError::UnexpectedEOF(_) => "E0102",
+            ParseError::TrailingTokens(_) => "E0103",
+            ParseError::TokenizerError(..) => "E0104",
+        }
+    }
+}
+
 pub fn parse(text: &str) -> ParseResult<Expr> {
-    let tokens = token::tokenize(text)?;
-    Parser::new(tokens).parse_expr()
+    parse_spanned(text).map(|(e, _)| e)
 }
 
-#[cfg(test)]
+/// Parses an expression together with the spans of all its sub-expressions.
+pub fn parse_spanned(text: &str) -> ParseResult<(Expr, SpanTree)> {
+    Parser::new(text)?.parse_expr()
+}
+
+/// Parses an expression, recovering from syntax errors: each error is recorded, and the parser skips ahead to the
+/// next `)` or `in` that an enclosing expression expects, replacing the skipped part by `Expr::Error`. Returns the
+/// partial expression, its spans, and all errors in the order of their positions, which are empty if and only if
+/// `parse_spanned` succeeds.
+pub fn parse_recovering(text: &str) -> (Expr, SpanTree, Vec<ParseError>) {
+    let mut parser = Parser::recovering(text);
+    let (e, spans) = parser.parse_expr().expect("errors are recovered from");
+    (e, spans, parser.recovered_errors())
+}
+
+/// Parses a sequence of definitions of the form `def x = e`, optionally separated by `;`.
+pub fn parse_program(text: &str) -> ParseResult<Program> {
+    parse_program_spanned(text).map(|(program, _)| program)
+}
+
+/// Parses a sequence of definitions together with the spans of their expressions, one span tree per definition.
+pub fn parse_program_spanned(text: &str) -> ParseResult<(Program, Vec<SpanTree>)> {
+    Parser::new(text)?.parse_program()
+}
+
+/// Like [`parse_recovering`], but parses a sequence of definitions, where the parser also skips ahead to the next `;`
+/// or `def`. Definitions without a name are left out.
+pub fn parse_program_recovering(text: &str) -> (Program, Vec<SpanTree>, Vec<ParseError>) {
+    let mut parser = Parser::recovering(text);
+    let (program, spans) = parser.parse_program().expect("errors are recovered from");
+    (program, spans, parser.recovered_errors())
+}
+
+/// Parses a type scheme `∀ α1 ... αn . τ`, or a type `τ` without quantifier.
 pub fn parse_poly(text: &str) -> ParseResult<Poly> {
-    let tokens = token::tokenize(text)?;
-    Parser::new(tokens).parse_poly()
+    let mut parser = Parser::new(text)?;
+    let sigma = parser.parse_poly_internal()?;
+    parser.expect_end()?;
+    Ok(sigma)
 }
 
-fn unexpected_token_error<T>(unexpected: &Token, expected: &str) -> ParseResult<T> {
+/// Parses a type: a type variable `a`, a type function applied to arguments `C τ1 ... τn`, a function type `τ1 → τ2`,
+/// or a recursive type `μ α . τ`.
+pub fn parse_mono(text: &str) -> ParseResult<Mono> {
+    let mut parser = Parser::new(text)?;
+    let tau = parser.parse_mono()?;
+    parser.expect_end()?;
+    Ok(tau)
+}
+
+fn unexpected_token_error<T>(unexpected: &Token, span: Span, expected: &str) -> ParseResult<T> {
     Err(ParseError::UnexpectedToken {
         expected: expected.to_string(),
         unexpected: unexpected.clone(),
+        span,
     })
 }
 
-struct Parser<I: Iterator<Item = Token>> {
-    tokens: Peekable<I>,
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    index: usize,
+    /// The position at the end of the text.
+    end: Position,
+    /// The errors recovered from, if recovering from errors.
+    errors: Option<Vec<ParseError>>,
+    /// The tokens at which parsing can resume after an error, because an enclosing construct expects them.
+    sync: Vec<Token>,
+    /// Whether the text ends inside an unterminated block comment, which the tokenizer already reported, so that the
+    /// end of the text is not reported as unexpected again.
+    unterminated_comment: bool,
 }
 
-impl<I: Iterator<Item = Token>> Parser<I> {
-    fn new(tokens: impl IntoIterator<IntoIter = I>) -> Self {
+impl Parser {
+    fn new(text: &str) -> ParseResult<Parser> {
+        Ok(Parser {
+            tokens: token::tokenize(text)?,
+            index: 0,
+            end: Position::end_of(text),
+            errors: None,
+            sync: Vec::new(),
+            unterminated_comment: false,
+        })
+    }
+
+    fn recovering(text: &str) -> Parser {
+        let (tokens, errors, unterminated_comment) = token::tokenize_recovering(text);
         Parser {
-            tokens: tokens.into_iter().peekable(),
+            tokens,
+            index: 0,
+            end: Position::end_of(text),
+            errors: Some(errors),
+            sync: Vec::new(),
+            unterminated_comment,
         }
     }
 
-    fn parse_expr(mut self) -> ParseResult<Expr> {
+    fn recovered_errors(self) -> Vec<ParseError> {
+        let mut errors = self.errors.unwrap_or_default();
+        errors.sort_by_key(|err| err.span().start.offset);
+        errors
+    }
+
+    fn parse_expr(&mut self) -> ParseResult<(Expr, SpanTree)> {
         let result = self.parse_expr_internal()?;
-        if self.current().is_err() {
-            Ok(result)
-        } else {
-            Err(ParseError::TrailingTokens)
+        self.expect_end()?;
+        Ok(result)
+    }
+
+    /// Fails with the first token left over, if any.
+    fn expect_end(&mut self) -> ParseResult<()> {
+        match self.current() {
+            Ok(_) => self.fail(ParseError::TrailingTokens(self.span())),
+            Err(_) => Ok(()),
         }
     }
 
-    #[cfg(test)]
-    fn parse_poly(mut self) -> ParseResult<Poly> {
-        let result = self.parse_poly_internal()?;
-        if self.current().is_err() {
-            Ok(result)
-        } else {
-            Err(ParseError::TrailingTokens)
+    fn parse_program(&mut self) -> ParseResult<(Program, Vec<SpanTree>)> {
+        use Token::*;
+
+        self.sync.extend([Def, Semicolon]);
+        let mut definitions = Vec::new();
+        let mut spans = Vec::new();
+        while let Ok(token) = self.current() {
+            if *token == Semicolon {
+                self.consume()?;
+                continue;
+            }
+            let start = self.index;
+            match self.parse_definition() {
+                Ok((definition, definition_spans)) => {
+                    definitions.push(definition);
+                    spans.push(definition_spans);
+                }
+                Err(err) => {
+                    self.resume_at(start, &err);
+                    self.fail(err)?;
+                    self.skip_to_sync();
+                }
+            }
         }
+        Ok((Program { definitions }, spans))
     }
 
-    fn current(&mut self) -> Result<&Token, ParseError> {
-        self.tokens.peek().ok_or(ParseError::UnexpectedEOF)
+    fn parse_definition(&mut self) -> ParseResult<(Definition, SpanTree)> {
+        use Token::*;
+
+        expect_token!(self, Def, "'def'");
+        let name = expect_variable!(self, "a variable");
+        expect_token!(self, Equals, "'='");
+        let (expr, spans) = self.parse_expr_internal()?;
+        Ok((Definition { name, expr }, spans))
     }
 
-    fn consume(&mut self) -> Result<Token, ParseError> {
-        self.tokens.next().ok_or(ParseError::UnexpectedEOF)
+    /// Records the error if recovering from errors, and returns it otherwise. An error at the same span as the
+    /// previous one is only recorded once, and an unexpected end of the text after an unterminated comment not at all.
+    fn fail(&mut self, err: ParseError) -> ParseResult<()> {
+        match &mut self.errors {
+            Some(_) if self.unterminated_comment && matches!(err, ParseError::UnexpectedEOF(_)) => Ok(()),
+            Some(errors) => {
+                if errors.last().map(ParseError::span) != Some(err.span()) {
+                    errors.push(err);
+                }
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    /// Continues after an error detected while parsing from the token at `start` at the offending token, which may
+    /// have been consumed already.
+    fn resume_at(&mut self, start: usize, err: &ParseError) {
+        if let Some(i) = self.tokens[start..].iter().position(|(_, span)| *span == err.span()) {
+            self.index = start + i;
+        }
+    }
+
+    /// Skips tokens up to the next synchronization point or the end of the text. Parentheses and `let`s opened while
+    /// skipping are skipped up to their matching `)` and `in`.
+    fn skip_to_sync(&mut self) {
+        use Token::*;
+
+        let (mut parens, mut lets) = (0, 0);
+        while let Some((token, _)) = self.tokens.get(self.index) {
+            match token {
+                LParen => parens += 1,
+                RParen if parens > 0 => parens -= 1,
+                Let => lets += 1,
+                In if lets > 0 => lets -= 1,
+                token if self.sync.contains(token) => break,
+                _ => (),
+            }
+            self.index += 1;
+        }
+    }
+
+    /// Consumes the token closing a construct that started with a synchronization point for it, which is removed
+    /// again. When recovering from errors, any other token is recorded as unexpected and skipped, along with all
+    /// tokens up to the next synchronization point, which is consumed if it is the closing token.
+    fn expect_closing(&mut self, closing: Token, expected: &str) -> ParseResult<()> {
+        let result = self.expect_closing_internal(&closing, expected);
+        self.sync.pop();
+        result
+    }
+
+    fn expect_closing_internal(&mut self, closing: &Token, expected: &str) -> ParseResult<()> {
+        let err = match self.tokens.get(self.index) {
+            Some((token, _)) if token == closing => {
+                self.index += 1;
+                return Ok(());
+            }
+            Some((token, span)) => ParseError::UnexpectedToken {
+                unexpected: token.clone(),
+                expected: expected.into(),
+                span: *span,
+            },
+            None => ParseError::UnexpectedEOF(self.span()),
+        };
+        self.fail(err)?;
+        self.skip_to_sync();
+        if let Ok(token) = self.current()
+            && token == closing
+        {
+            self.index += 1;
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> Result<&Token, ParseError> {
+        match self.tokens.get(self.index) {
+            Some((token, _)) => Ok(token),
+            None => Err(ParseError::UnexpectedEOF(self.span())),
+        }
+    }
+
+    /// The span of the current token, or the empty span at the end of the text.
+    fn span(&self) -> Span {
+        self.span_at(self.index)
     }
 
-    fn parse_expr_internal(&mut self) -> ParseResult<Expr> {
+    fn span_at(&self, index: usize) -> Span {
+        match self.tokens.get(index) {
+            Some((_, span)) => *span,
+            None => Span::at(self.end),
+        }
+    }
+
+    fn consume(&mut self) -> Result<(Token, Span), ParseError> {
+        let token = self.tokens.get(self.index).cloned();
+        let token = token.ok_or(ParseError::UnexpectedEOF(self.span()))?;
+        self.index += 1;
+        Ok(token)
+    }
+
+    /// The span tree of an expression starting at the given position and ending with the last consumed token.
+    fn span_tree(&self, start: Position, children: Vec<SpanTree>) -> SpanTree {
+        let (_, last) = &self.tokens[self.index - 1];
+        SpanTree::new(Span::new(start, last.end), children)
+    }
+
+    /// Parses an expression. When recovering from errors, an erroneous expression becomes an error node spanning the
+    /// tokens up to the next synchronization point.
+    fn parse_expr_internal(&mut self) -> ParseResult<(Expr, SpanTree)> {
+        let start = self.index;
+        match self.parse_expr_or_fail() {
+            Err(err) if self.errors.is_some() => {
+                let start_position = self.span_at(start).start;
+                self.resume_at(start, &err);
+                self.fail(err)?;
+                self.skip_to_sync();
+                let end = if self.index > start {
+                    self.tokens[self.index - 1].1.end
+                } else {
+                    start_position
+                };
+                Ok((Expr::Error, SpanTree::new(Span::new(start_position, end), Vec::new())))
+            }
+            result => result,
+        }
+    }
+
+    fn parse_expr_or_fail(&mut self) -> ParseResult<(Expr, SpanTree)> {
         use Token::*;
 
+        let start = self.span().start;
         match self.current()? {
             Lambda => {
                 expect_token!(self, Lambda, "'lambda', or 'λ'");
                 let x = expect_variable!(self, "a variable");
                 expect_token!(self, Dot, "'.'");
-                let e = self.parse_expr_internal()?;
+                let (e, spans) = self.parse_expr_internal()?;
 
-                Ok(Expr::abs(x, e))
+                Ok((Expr::abs(x, e), self.span_tree(start, vec![spans])))
             }
             Let => {
                 expect_token!(self, Let, "'let'");
                 let x = expect_variable!(self, "a variable");
                 expect_token!(self, Equals, "'='");
-                let e1 = self.parse_expr_internal()?;
-                expect_token!(self, In, "'in'");
-                let e2 = self.parse_expr_internal()?;
+                self.sync.push(In);
+                let (e1, spans1) = self.parse_expr_internal()?;
+                self.expect_closing(In, "'in'")?;
+                let (e2, spans2) = self.parse_expr_internal()?;
 
-                Ok(Expr::r#let(x, e1, e2))
+                Ok((Expr::r#let(x, e1, e2), self.span_tree(start, vec![spans1, spans2])))
             }
 
-            LParen | Var(..) => self.parse_app(),
+            LParen | Var(..) | Fold | Unfold => self.parse_app(),
 
-            token => unexpected_token_error(token, "'lambda', 'λ', 'let', '(', or a variable"),
+            token => unexpected_token_error(
+                token,
+                self.span(),
+                "'lambda', 'λ', 'let', 'fold', 'unfold', '(', or a variable",
+            ),
         }
     }
 
-    fn parse_app(&mut self) -> ParseResult<Expr> {
+    fn parse_app(&mut self) -> ParseResult<(Expr, SpanTree)> {
         use Token::*;
 
-        let mut e = self.parse_atomic_expr()?;
+        let start = self.span().start;
+        let (mut e, mut spans) = self.parse_atomic_expr()?;
 
-        while let Ok(Var(..) | LParen) = self.current() {
-            let arg = self.parse_atomic_expr()?;
+        while let Ok(Var(..) | LParen | Fold | Unfold) = self.current() {
+            let (arg, arg_spans) = self.parse_atomic_expr()?;
             e = Expr::app(e, arg);
+            spans = self.span_tree(start, vec![spans, arg_spans]);
         }
 
-        Ok(e)
+        Ok((e, spans))
     }
 
-    fn parse_atomic_expr(&mut self) -> ParseResult<Expr> {
+    fn parse_atomic_expr(&mut self) -> ParseResult<(Expr, SpanTree)> {
         use Token::*;
 
+        let start = self.span().start;
         match self.current()? {
             LParen => {
                 expect_token!(self, LParen, "'('");
-                let e = self.parse_expr_internal()?;
-                expect_token!(self, RParen, "')'");
+                self.sync.push(RParen);
+                let result = self.parse_expr_internal()?;
+                self.expect_closing(RParen, "')'")?;
 
-                Ok(e)
+                Ok(result)
             }
             Var(x) => {
                 let e = Expr::var(x);
                 self.consume()?;
 
-                Ok(e)
+                Ok((e, self.span_tree(start, Vec::new())))
+            }
+            Fold => {
+                expect_token!(self, Fold, "'fold'");
+                let tau = self.parse_rec_annotation()?;
+                let (e, spans) = self.parse_atomic_expr()?;
+
+                Ok((Expr::fold(tau, e), self.span_tree(start, vec![spans])))
+            }
+            Unfold => {
+                expect_token!(self, Unfold, "'unfold'");
+                let tau = self.parse_rec_annotation()?;
+                let (e, spans) = self.parse_atomic_expr()?;
+
+                Ok((Expr::unfold(tau, e), self.span_tree(start, vec![spans])))
             }
 
-            token => unexpected_token_error(token, "'(', or a variable"),
+            token => unexpected_token_error(token, self.span(), "'(', 'fold', 'unfold', or a variable"),
         }
     }
 
-    #[cfg(test)]
+    /// Parses the annotation `[μα . τ]` of `fold` and `unfold`.
+    fn parse_rec_annotation(&mut self) -> ParseResult<Mono> {
+        use Token::*;
+
+        expect_token!(self, LBracket, "'['");
+        match self.current()? {
+            Mu => (),
+            token => return unexpected_token_error(token, self.span(), "'mu', or 'μ'"),
+        }
+        let tau = self.parse_mono()?;
+        expect_token!(self, RBracket, "']'");
+
+        Ok(tau)
+    }
+
     fn parse_poly_internal(&mut self) -> ParseResult<Poly> {
         use Token::*;
 
         match self.current()? {
             ForAll => {
-                expect_token!(self, ForAll, "'forall', or '∀' ");
+                expect_token!(self, ForAll, "'forall', or '∀'");
 
                 let mut vars: HashSet<TypeVar> = [expect_variable!(self, "a type variable")].into();
                 while let Ok(Var(_)) = self.current() {
@@ -161,18 +454,29 @@ impl<I: Iterator<Item = Token>> Parser<I> {
 
                 Ok(Poly(vars, tau))
             }
-            Var(..) | VarCap(..) => {
+            Var(..) | VarCap(..) | LParen | Mu => {
                 let sigma = Poly::mono(self.parse_mono()?);
                 Ok(sigma)
             }
-            token => unexpected_token_error(token, "'(', or a type variable or type function"),
+            token => unexpected_token_error(
+                token,
+                self.span(),
+                "'forall', '∀', 'mu', 'μ', '(', or a type variable or type function",
+            ),
         }
     }
 
-    #[cfg(test)]
     fn parse_mono(&mut self) -> ParseResult<Mono> {
         use Token::*;
 
+        if let Ok(Mu) = self.current() {
+            expect_token!(self, Mu, "'mu', or 'μ'");
+            let alpha = expect_variable!(self, "a type variable");
+            expect_token!(self, Dot, "'.'");
+            let tau = self.parse_mono()?;
+            return Ok(Mono::Rec(alpha, Box::new(tau)));
+        }
+
         let tau1 = self.parse_mono_arrow_arg()?;
 
         if let Ok(Arrow) = self.current() {
@@ -185,7 +489,6 @@ impl<I: Iterator<Item = Token>> Parser<I> {
     }
 
     #[allow(nonstandard_style)]
-    #[cfg(test)]
     fn parse_mono_arrow_arg(&mut self) -> ParseResult<Mono> {
         use Token::*;
 
@@ -201,11 +504,10 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 Ok(Mono::App(C, taus))
             }
             Var(..) | LParen => Ok(self.parse_atomic_mono()?),
-            token => unexpected_token_error(token, "'(', or a type variable or type function"),
+            token => unexpected_token_error(token, self.span(), "'(', or a type variable or type function"),
         }
     }
 
-    #[cfg(test)]
     fn parse_atomic_mono(&mut self) -> ParseResult<Mono> {
         use Token::*;
 
@@ -225,7 +527,7 @@ impl<I: Iterator<Item = Token>> Parser<I> {
                 let alpha = expect_varcap!(self, "a type function");
                 Ok(Mono::nullary(alpha))
             }
-            token => unexpected_token_error(token, "'(', or a type variable or type function"),
+            token => unexpected_token_error(token, self.span(), "'(', or a type variable or type function"),
         }
     }
 }
@@ -264,11 +566,11 @@ mod tests {
     #[test]
     fn test_parse_malformed() {
         assert!(matches!(parse("lambda x y"), Err(ParseError::UnexpectedToken { .. })));
-        assert!(matches!(parse("lambda x ."), Err(ParseError::UnexpectedEOF)));
+        assert!(matches!(parse("lambda x ."), Err(ParseError::UnexpectedEOF(..))));
         assert!(matches!(parse("lambda x ? y"), Err(ParseError::TokenizerError(..))));
-        assert!(matches!(parse("lambda x . y )"), Err(ParseError::TrailingTokens)));
+        assert!(matches!(parse("lambda x . y )"), Err(ParseError::TrailingTokens(..))));
         assert!(matches!(parse("let x in y"), Err(ParseError::UnexpectedToken { .. })));
-        assert!(matches!(parse("let x = y in"), Err(ParseError::UnexpectedEOF)));
+        assert!(matches!(parse("let x = y in"), Err(ParseError::UnexpectedEOF(..))));
         assert!(matches!(
             parse("let lambda = y in z"),
             Err(ParseError::UnexpectedToken { .. })
@@ -281,7 +583,7 @@ mod tests {
             parse("lambda x . y z"),
             Ok(Expr::abs("x", Expr::app(Expr::var("y"), Expr::var("z"))))
         );
-        assert!(matches!(parse("z lambda x . y"), Err(ParseError::TrailingTokens)));
+        assert!(matches!(parse("z lambda x . y"), Err(ParseError::TrailingTokens(..))));
         assert_eq!(
             parse("(lambda x . y) z"),
             Ok(Expr::app(Expr::abs("x", Expr::var("y")), Expr::var("z")))
@@ -302,7 +604,7 @@ mod tests {
                 Expr::app(Expr::var("z"), Expr::var("a"))
             ))
         );
-        assert!(matches!(parse("a let x = y in y"), Err(ParseError::TrailingTokens)));
+        assert!(matches!(parse("a let x = y in y"), Err(ParseError::TrailingTokens(..))));
         assert_eq!(
             parse("(let x = y in z) a"),
             Ok(Expr::app(
@@ -318,4 +620,215 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_parse_program() {
+        let program = parse_program("def id = λ x . x\ndef k = λ x . λ y . x\ndef a = k id id").unwrap();
+
+        let names: Vec<&str> = program.definitions.iter().map(|def| def.name.as_str()).collect();
+        assert_eq!(names, ["id", "k", "a"]);
+        assert_eq!(
+            program.definitions[2].expr,
+            Expr::app(Expr::app(Expr::var("k"), Expr::var("id")), Expr::var("id"))
+        );
+        assert_eq!(parse_program(""), Ok(Program::default()));
+        assert!(matches!(
+            parse_program("def x = y in z"),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+        assert!(matches!(parse_program("x"), Err(ParseError::UnexpectedToken { .. })));
+        assert!(matches!(parse("def"), Err(ParseError::UnexpectedToken { .. })));
+    }
+
+    #[test]
+    fn test_parse_fold_and_unfold() {
+        let tau = Mono::Rec(
+            "t".into(),
+            Box::new(Mono::arrow(Mono::Var("t".into()), Mono::Var("a".into()))),
+        );
+
+        assert_eq!(
+            parse("unfold [μ t . t → a] x x"),
+            Ok(Expr::app(Expr::unfold(tau.clone(), Expr::var("x")), Expr::var("x")))
+        );
+        assert_eq!(
+            parse("fold [mu t . t to a] (λ x . x)"),
+            Ok(Expr::fold(tau.clone(), Expr::abs("x", Expr::var("x"))))
+        );
+        assert_eq!(
+            parse("f (fold [μ t . t → a] x)"),
+            Ok(Expr::app(Expr::var("f"), Expr::fold(tau, Expr::var("x"))))
+        );
+        assert!(matches!(
+            parse("fold [t → a] x"),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+        assert!(matches!(parse("fold x"), Err(ParseError::UnexpectedToken { .. })));
+    }
+
+    #[test]
+    fn test_parse_recursive_type() {
+        let sigma = parse_poly("∀ a . (μ t . t → a) → μ s . s").unwrap();
+
+        assert_eq!(sigma.to_string(), "∀ a . (μ t . t → a) → μ s . s");
+    }
+
+    #[test]
+    fn test_parse_poly_and_mono() {
+        let tau = Mono::arrow(
+            Mono::arrow(Mono::nullary("Int"), Mono::Var("a".into())),
+            Mono::App("List".into(), vec![Mono::Var("a".into())]),
+        );
+
+        assert_eq!(parse_mono("(Int → a) → List a"), Ok(tau.clone()));
+        assert_eq!(parse_poly("(Int → a) → List a"), Ok(Poly::mono(tau.clone())));
+        assert_eq!(
+            parse_poly("forall a . (Int to a) to List a"),
+            Ok(Poly(HashSet::from(["a".into()]), tau))
+        );
+        assert_eq!(parse_mono("μ t . t → a").unwrap().to_string(), "μ t . t → a");
+
+        assert!(matches!(parse_mono("Int → a )"), Err(ParseError::TrailingTokens(_))));
+        assert!(matches!(parse_poly("∀ a . a a"), Err(ParseError::TrailingTokens(_))));
+        assert!(matches!(parse_mono("∀ a . a"), Err(ParseError::UnexpectedToken { .. })));
+        assert!(matches!(parse_poly("Int →"), Err(ParseError::UnexpectedEOF(_))));
+        assert!(matches!(parse_poly("λ"), Err(ParseError::UnexpectedToken { .. })));
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let tokens = token::tokenize("λ x .\n  foo").unwrap();
+        let spans: Vec<(usize, usize, usize)> = tokens
+            .iter()
+            .map(|(_, span)| (span.start.offset, span.start.line, span.start.column))
+            .collect();
+
+        assert_eq!(spans, [(0, 1, 1), (3, 1, 3), (5, 1, 5), (9, 2, 3)]);
+        assert_eq!(tokens[3].1.end.offset, 12);
+    }
+
+    #[test]
+    fn test_parse_identifiers() {
+        assert_eq!(
+            parse("λ x' . my_fun'' _ x1"),
+            Ok(Expr::abs(
+                "x'",
+                Expr::app(Expr::app(Expr::var("my_fun''"), Expr::var("_")), Expr::var("x1"))
+            ))
+        );
+        assert_eq!(parse("λα.α"), Ok(Expr::abs("α", Expr::var("α"))));
+        assert_eq!(
+            parse("λ x . x'y"),
+            Ok(Expr::abs("x", Expr::app(Expr::var("x'"), Expr::var("y"))))
+        );
+        assert_eq!(
+            parse_mono("Σ α → Ñ"),
+            Ok(Mono::arrow(
+                Mono::App("Σ".into(), vec![Mono::Var("α".into())]),
+                Mono::nullary("Ñ")
+            ))
+        );
+        assert_eq!(
+            parse_mono("_a → _12b"),
+            Ok(Mono::arrow(Mono::Var("_a".into()), Mono::Var("_12b".into())))
+        );
+
+        assert!(matches!(parse("λ _1 . _1"), Err(ParseError::TokenizerError(..))));
+        assert!(matches!(parse("'x"), Err(ParseError::TokenizerError(..))));
+        assert!(matches!(parse("1x"), Err(ParseError::TokenizerError(..))));
+    }
+
+    #[test]
+    fn test_skip_comments() {
+        assert_eq!(
+            parse("-- the identity\nλ x . {- the {- nested -} body -} x -- done"),
+            Ok(Expr::abs("x", Expr::var("x")))
+        );
+        assert_eq!(parse("{--} x {---}"), Ok(Expr::var("x")));
+        assert_eq!(parse_poly("∀ a . a {- argument -} → a --"), parse_poly("∀ a . a → a"));
+
+        let err = parse("λ x . x\n  {- {- -} x").unwrap_err();
+        assert!(matches!(&err, ParseError::TokenizerError(msg, _) if msg == "unterminated block comment"));
+        assert_eq!((err.span().start.line, err.span().start.column), (2, 3));
+        assert!(matches!(parse("x - y"), Err(ParseError::TokenizerError(..))));
+        assert!(matches!(parse("x {y"), Err(ParseError::TokenizerError(..))));
+
+        // The end of the text inside the comment is not reported again.
+        let (_, _, errors) = parse_recovering("{- unterminated");
+        assert!(matches!(&errors[..], [ParseError::TokenizerError(msg, _)] if msg == "unterminated block comment"));
+        let (_, _, errors) = parse_program_recovering("def x = λ y . {- unterminated");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_spanned() {
+        let text = "let id = λ x . x in (id) id";
+        let (e, spans) = parse_spanned(text).unwrap();
+        let Expr::Let(_, e1, e2) = &e else { panic!() };
+        let Expr::App(f, arg) = e2.as_ref() else { panic!() };
+
+        let text_of = |sub: &Expr| spans.find(&e, sub).unwrap().text(text);
+
+        assert_eq!(text_of(&e), text);
+        assert_eq!(text_of(e1), "λ x . x");
+        assert_eq!(text_of(e2), "(id) id");
+        assert_eq!(text_of(f), "id");
+        assert_eq!(spans.find(&e, arg).unwrap().start.column, 26);
+    }
+
+    #[test]
+    fn test_parse_error_spans() {
+        let column = |text: &str| parse(text).unwrap_err().span().start.column;
+
+        assert_eq!(column("let x in y"), 7);
+        assert_eq!(column("λ x . y )"), 9);
+        assert_eq!(column("λ x . y ? z"), 9);
+        assert_eq!(parse("λ x .").unwrap_err().span().start.offset, "λ x .".len());
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        let (e, _, errors) = parse_recovering("(λ x . ) (let y = in y) $");
+
+        let columns: Vec<usize> = errors.iter().map(|err| err.span().start.column).collect();
+        assert_eq!(columns, [8, 19, 25]);
+        assert_eq!(
+            e,
+            Expr::app(
+                Expr::abs("x", Expr::Error),
+                Expr::r#let("y", Expr::Error, Expr::var("y"))
+            )
+        );
+        assert!(e.has_errors());
+
+        let (e, _, errors) = parse_recovering("f (x y");
+        assert_eq!(
+            errors,
+            [ParseError::UnexpectedEOF(Span::at(Position::end_of("f (x y")))]
+        );
+        assert_eq!(e, Expr::app(Expr::var("f"), Expr::app(Expr::var("x"), Expr::var("y"))));
+
+        for text in ["let x in y", "λ x . y )", "λ x . y ? z", "λ x .", ""] {
+            let (_, _, errors) = parse_recovering(text);
+            assert_eq!(errors, [parse(text).unwrap_err()]);
+        }
+        assert_eq!(parse_recovering("λ x . x").2, []);
+    }
+
+    #[test]
+    fn test_parse_program_recovering() {
+        let text = "def a = ( ; def = x def b = a; def c = λ x . x";
+        let (program, spans, errors) = parse_program_recovering(text);
+
+        let names: Vec<&str> = program.definitions.iter().map(|def| def.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(program.definitions[0].expr, Expr::Error);
+        assert_eq!(errors.len(), 2);
+        let texts: Vec<&str> = spans.iter().map(|tree| tree.span.text(text)).collect();
+        assert_eq!(texts, ["", "a", "λ x . x"]);
+        assert_eq!(
+            parse_program("def a = x; def b = a;").map(|p| p.definitions.len()),
+            Ok(2)
+        );
+    }
 }
@@ -1,9 +1,9 @@
 #[macro_export]
 macro_rules! expect_token {
     ($parser:expr, $pat:pat, $expected:expr) => {{
-        let token = $parser.consume()?;
+        let (token, span) = $parser.consume()?;
         let $pat = token else {
-            return unexpected_token_error(&token, $expected);
+            return unexpected_token_error(&token, span, $expected);
         };
         token
     }};
@@ -12,9 +12,9 @@ macro_rules! expect_token {
 #[macro_export]
 macro_rules! expect_variable {
     ($parser:expr, $expected:expr) => {{
-        let token = $parser.consume()?;
+        let (token, span) = $parser.consume()?;
         let Var(x) = token else {
-            return unexpected_token_error(&token, $expected);
+            return unexpected_token_error(&token, span, $expected);
         };
         x
     }};
@@ -23,9 +23,9 @@ macro_rules! expect_variable {
 #[macro_export]
 macro_rules! expect_varcap {
     ($parser:expr, $expected:expr) => {{
-        let token = $parser.consume()?;
+        let (token, span) = $parser.consume()?;
         let VarCap(x) = token else {
-            return unexpected_token_error(&token, $expected);
+            return unexpected_token_error(&token, span, $expected);
         };
         x
     }};
@@ -1,6 +1,10 @@
 use std::{char, iter::Peekable};
 
-use crate::{consume_and_return, parse::ParseError};
+use crate::{
+    consume_and_return,
+    parse::ParseError,
+    span::{Position, Span},
+};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Token {
@@ -10,24 +14,73 @@ pub enum Token {
     Dot,
     Let,
     In,
+    Def,
     Equals,
     LParen,
     RParen,
     ForAll,
     Arrow,
+    Mu,
+    LBracket,
+    RBracket,
+    Fold,
+    Unfold,
+    Semicolon,
 }
 
-pub fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+/// Splits the text into tokens, each together with its span.
+pub fn tokenize(text: &str) -> Result<Vec<(Token, Span)>, ParseError> {
     Tokenizer::new(text.chars()).collect()
 }
 
+/// Like [`tokenize`], but skips unexpected characters, returning the errors about them along with the tokens, and
+/// whether the text ends inside an unterminated block comment.
+pub fn tokenize_recovering(text: &str) -> (Vec<(Token, Span)>, Vec<ParseError>, bool) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    let mut tokenizer = Tokenizer::new(text.chars());
+    for result in &mut tokenizer {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(err) => errors.push(err),
+        }
+    }
+    (tokens, errors, tokenizer.unterminated_comment)
+}
+
+/// Whether the character can start an identifier, per `XID_Start`, or is an underscore. The letters `λ` and `μ` are
+/// tokens of their own instead.
+fn is_identifier_start(c: char) -> bool {
+    (unicode_ident::is_xid_start(c) || c == '_') && !matches!(c, 'λ' | 'μ')
+}
+
+/// Whether the character can continue an identifier, per `XID_Continue`, which includes digits and underscores.
+fn is_identifier_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c) && !matches!(c, 'λ' | 'μ')
+}
+
+/// Whether the identifier has the form `_N` of the type variables that the inference algorithms create, which would
+/// be confused with them.
+fn is_fresh_var(token: &str) -> bool {
+    token
+        .strip_prefix('_')
+        .is_some_and(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
 struct Tokenizer<I: Iterator<Item = char>> {
     text: Peekable<I>,
+    position: Position,
+    /// Whether a block comment was still open at the end of the text.
+    unterminated_comment: bool,
 }
 
 impl<I: Iterator<Item = char>> Tokenizer<I> {
     fn new(text: I) -> Self {
-        Tokenizer { text: text.peekable() }
+        Tokenizer {
+            text: text.peekable(),
+            position: Position::start(),
+            unterminated_comment: false,
+        }
     }
 
     fn current(&mut self) -> Option<&char> {
@@ -39,25 +92,93 @@ impl<I: Iterator<Item = char>> Tokenizer<I> {
     }
 
     fn consume(&mut self) {
-        self.text.next();
+        if let Some(c) = self.text.next() {
+            self.position = self.position.advance(c);
+        }
     }
 
+    /// Skips whitespace and comments: line comments from `--` to the end of the line, and block comments between `{-`
+    /// and `-}`, which may be nested.
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), ParseError> {
+        loop {
+            while self.on_whitespace() {
+                self.consume()
+            }
+
+            let start = self.position;
+            let c = match self.current() {
+                Some(&c @ ('-' | '{')) => c,
+                _ => return Ok(()),
+            };
+            self.consume();
+            match (c, self.current()) {
+                ('-', Some('-')) => {
+                    while self.current().is_some_and(|&c| c != '\n') {
+                        self.consume()
+                    }
+                }
+                ('{', Some('-')) => {
+                    self.consume();
+                    self.skip_block_comment(start)?;
+                }
+                _ => return Err(self.unexpected_character(c, start)),
+            }
+        }
+    }
+
+    /// Skips the rest of a block comment starting at `start`, up to the matching `-}`.
+    fn skip_block_comment(&mut self, start: Position) -> Result<(), ParseError> {
+        let mut depth = 1;
+        while depth > 0 {
+            let Some(&c) = self.current() else {
+                self.unterminated_comment = true;
+                return Err(ParseError::TokenizerError(
+                    "unterminated block comment".into(),
+                    Span::new(start, start.advance('{').advance('-')),
+                ));
+            };
+            self.consume();
+            match (c, self.current()) {
+                ('{', Some('-')) => {
+                    self.consume();
+                    depth += 1;
+                }
+                ('-', Some('}')) => {
+                    self.consume();
+                    depth -= 1;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn unexpected_character(&self, c: char, start: Position) -> ParseError {
+        ParseError::TokenizerError(format!("unexpected character '{c}'"), Span::new(start, self.position))
+    }
+
+    /// Consumes an identifier: Unicode letters, digits, and underscores, starting with a letter or an underscore, and
+    /// followed by any number of primes.
     fn consume_keyword_or_var(&mut self) -> String {
         use peeking_take_while::PeekableExt;
-        self.text.peeking_take_while(|c| c.is_ascii_alphanumeric()).collect()
+        let mut token: String = self.text.peeking_take_while(|&c| is_identifier_continue(c)).collect();
+        token.extend(self.text.peeking_take_while(|&c| c == '\''));
+        self.position = token.chars().fold(self.position, Position::advance);
+        token
     }
 }
 
 impl<I: Iterator<Item = char>> Iterator for Tokenizer<I> {
-    type Item = Result<Token, ParseError>;
+    type Item = Result<(Token, Span), ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         use Token::*;
 
-        while self.on_whitespace() {
-            self.consume()
+        if let Err(err) = self.skip_whitespace_and_comments() {
+            return Some(Err(err));
         }
 
+        let start = self.position;
         let token = match self.current()? {
             '.' => consume_and_return!(self, Dot),
             'λ' => consume_and_return!(self, Lambda),
@@ -66,23 +187,40 @@ impl<I: Iterator<Item = char>> Iterator for Tokenizer<I> {
             ')' => consume_and_return!(self, RParen),
             '∀' => consume_and_return!(self, ForAll),
             '→' => consume_and_return!(self, Arrow),
+            'μ' => consume_and_return!(self, Mu),
+            '[' => consume_and_return!(self, LBracket),
+            ']' => consume_and_return!(self, RBracket),
+            ';' => consume_and_return!(self, Semicolon),
 
-            c if c.is_ascii_alphabetic() => {
+            &c if is_identifier_start(c) => {
                 let token = self.consume_keyword_or_var();
                 match token.as_str() {
                     "lambda" => Lambda,
                     "let" => Let,
                     "in" => In,
+                    "def" => Def,
                     "forall" => ForAll,
                     "to" => Arrow,
-                    token if token.chars().next().unwrap().is_ascii_lowercase() => Var(token.into()),
-                    token => VarCap(token.into()),
+                    "mu" => Mu,
+                    "fold" => Fold,
+                    "unfold" => Unfold,
+                    token if is_fresh_var(token) => {
+                        return Some(Err(ParseError::TokenizerError(
+                            format!("'{token}' is reserved for type variables introduced by inference"),
+                            Span::new(start, self.position),
+                        )));
+                    }
+                    token if token.chars().next().unwrap().is_uppercase() => VarCap(token.into()),
+                    token => Var(token.into()),
                 }
             }
 
-            c => return Some(Err(ParseError::TokenizerError(format!("unexpected character '{c}'")))),
+            &c => {
+                self.consume();
+                return Some(Err(self.unexpected_character(c, start)));
+            }
         };
 
-        Some(Ok(token))
+        Some(Ok((token, Span::new(start, self.position))))
     }
 }
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use crate::{
+    algorithm_j::{InferenceResult, LocatedError, infer_recovering_with_subst},
+    ctxt::Ctxt,
+    expr::{Expr, Var},
+    typed_expr::TypedExpr,
+    types::{Poly, Subst},
+};
+
+/// A top-level definition `def x = e`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Definition {
+    pub name: Var,
+    pub expr: Expr,
+}
+
+/// A sequence of definitions, each of which may refer to the ones before it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Program {
+    pub definitions: Vec<Definition>,
+}
+
+impl Program {
+    /// Replaces the definition of the same name, or appends the definition if there is none.
+    pub fn define(&mut self, definition: Definition) {
+        match self.definitions.iter_mut().find(|def| def.name == definition.name) {
+            Some(def) => *def = definition,
+            None => self.definitions.push(definition),
+        }
+    }
+}
+
+/// The result of inferring the type of a definition.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Typing<'a> {
+    pub name: Var,
+    pub result: InferenceResult<Poly>,
+    /// Whether the result was taken from the cache instead of being inferred again.
+    pub cached: bool,
+    /// If and only if inference failed and the result was not taken from the cache, the definition, the type of every
+    /// sub-expression, and all errors with the sub-expressions they were detected at, as found by recovering from the
+    /// errors (see `algorithm_j::infer_recovering_typed`).
+    pub failure: Option<(&'a Definition, TypedExpr, Vec<LocatedError<'a>>)>,
+}
+
+/// A definition's expression together with the types of its free variables, which determine its type.
+type CacheKey = (Expr, Vec<(Var, Option<String>)>);
+
+/// Infers the types of the definitions of a program in order, each in the context extended by the definitions before
+/// it, and refined by the types their free type variables were unified with. Results are cached between runs, so that
+/// after an edit only the changed definitions and the definitions whose dependencies changed their types are inferred
+/// again.
+#[allow(nonstandard_style)]
+pub struct Session {
+    Gamma: Ctxt,
+    /// The type of each definition, with the substitution it applies to the free type variables of the context.
+    cache: HashMap<CacheKey, InferenceResult<(Poly, Subst)>>,
+}
+
+impl Session {
+    #[allow(nonstandard_style)]
+    pub fn new(Gamma: Ctxt) -> Session {
+        Session {
+            Gamma,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Infers the types of all definitions, and returns them together with the context extended by all definitions
+    /// whose types could be inferred.
+    #[allow(nonstandard_style)]
+    pub fn infer<'a>(&mut self, program: &'a Program) -> (Vec<Typing<'a>>, Ctxt) {
+        let mut Gamma = self.Gamma.clone();
+        let mut cache = HashMap::new();
+        let mut typings = Vec::new();
+
+        for definition in &program.definitions {
+            let Definition { name, expr, .. } = definition;
+            let key = cache_key(expr, &Gamma);
+            let (result, cached, failure) = match self.cache.get(&key).cloned() {
+                Some(result) => (result, true, None),
+                None => {
+                    // Inferring the type while recovering from errors finds all errors at once.
+                    let (sigma, S, typed, errors) = infer_recovering_with_subst(expr, &Gamma);
+                    match errors.first() {
+                        None => (Ok((sigma, S)), false, None),
+                        Some(err) => (Err(err.error.clone()), false, Some((definition, typed, errors))),
+                    }
+                }
+            };
+            if let Ok((sigma, S)) = &result {
+                Gamma = Gamma.apply(S).bind(name, sigma.clone());
+            }
+            cache.insert(key, result.clone());
+            typings.push(Typing {
+                name: name.clone(),
+                result: result.map(|(sigma, _)| sigma),
+                cached,
+                failure,
+            });
+        }
+
+        self.cache = cache;
+        (typings, Gamma)
+    }
+}
+
+#[allow(nonstandard_style)]
+fn cache_key(e: &Expr, Gamma: &Ctxt) -> CacheKey {
+    let mut dependencies: Vec<(Var, Option<String>)> = e
+        .free()
+        .into_iter()
+        .map(|x| {
+            let sigma = Gamma.get(&x).map(|sigma| sigma.clone().normalize().to_string());
+            (x, sigma)
+        })
+        .collect();
+    dependencies.sort();
+    (e.clone(), dependencies)
+}
+
+impl std::fmt::Display for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "def {} = {}", self.name, self.expr)
+    }
+}
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for definition in &self.definitions {
+            writeln!(f, "{}", definition)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::InferenceError,
+        parse::{parse, parse_poly, parse_program, parse_program_spanned},
+    };
+
+    use super::*;
+
+    fn cached(typings: &[Typing]) -> Vec<bool> {
+        typings.iter().map(|typing| typing.cached).collect()
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_infer_program() {
+        let program = parse_program("def id = λ x . x def k = λ x . λ y . x def a = k id").unwrap();
+
+        let (typings, Gamma) = Session::new(Ctxt::new()).infer(&program);
+
+        assert_eq!(typings[2].result, Ok(parse_poly("∀ a b . b → a → a").unwrap()));
+        assert_eq!(Gamma.get(&"a".into()), Some(&parse_poly("∀ a b . b → a → a").unwrap()));
+        assert_eq!(cached(&typings), [false, false, false]);
+    }
+
+    #[test]
+    fn test_reinfer_only_changed_definitions_and_dependents() {
+        let mut program = parse_program("def id = λ x . x def k = λ x . λ y . x def a = id k def b = k id").unwrap();
+        let mut session = Session::new(Ctxt::new());
+        session.infer(&program);
+
+        let (typings, _) = session.infer(&program);
+        assert_eq!(cached(&typings), [true, true, true, true]);
+
+        program.define(Definition {
+            name: "k".into(),
+            expr: parse("λ x . λ y . y").unwrap(),
+        });
+        let (typings, _) = session.infer(&program);
+        assert_eq!(cached(&typings), [true, false, false, false]);
+
+        // An edit that does not change the type of the definition does not affect its dependents.
+        program.define(Definition {
+            name: "id".into(),
+            expr: parse("λ y . y").unwrap(),
+        });
+        let (typings, _) = session.infer(&program);
+        assert_eq!(cached(&typings), [false, true, true, true]);
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_thread_context_refinements() {
+        let Gamma = Ctxt::new()
+            .bind("unify", parse_poly("∀ x . x → x → x").unwrap())
+            .bind("n", parse_poly("Int").unwrap())
+            .bind("x", parse_poly("a").unwrap());
+        let program = parse_program("def y = unify x n def z = x").unwrap();
+
+        let (typings, Gamma) = Session::new(Gamma).infer(&program);
+
+        // The first definition unifies `a` with `Int`, which the second one observes.
+        assert_eq!(typings[1].result, Ok(parse_poly("Int").unwrap()));
+        assert_eq!(Gamma.get(&"x".into()), Some(&parse_poly("Int").unwrap()));
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_refinements_with_fresh_vars_do_not_clash() {
+        let Gamma = Ctxt::new()
+            .bind("x", parse_poly("a").unwrap())
+            .bind("n", parse_poly("Int").unwrap());
+        let program = parse_program("def d1 = x n def d2 = λ y . λ z . z def d3 = d2 n n").unwrap();
+
+        let (typings, Gamma) = Session::new(Gamma).infer(&program);
+
+        // The first definition refines the type of `x` to `Int → _1`, which must not affect the later definitions.
+        let results: Vec<String> = typings
+            .into_iter()
+            .map(|typing| typing.result.unwrap().normalize().to_string())
+            .collect();
+        assert_eq!(results, ["_1", "∀ a b . a → b → b", "Int"]);
+        let types: Vec<String> = ["x", "d1", "d2"]
+            .into_iter()
+            .map(|x| Gamma.get(&x.into()).unwrap().clone().normalize().to_string())
+            .collect();
+        assert_eq!(types, ["Int → _1", "_1", "∀ a b . a → b → b"]);
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_failed_definition_is_unbound() {
+        let program = parse_program("def f = λ x . x x def g = f").unwrap();
+
+        let (typings, Gamma) = Session::new(Ctxt::new()).infer(&program);
+
+        assert!(matches!(typings[0].result, Err(InferenceError::RecursiveType(..))));
+        assert!(matches!(&typings[1].result, Err(InferenceError::UnknownVar { name, .. }) if name == "f"));
+        assert!(Gamma.is_empty());
+    }
+
+    #[test]
+    fn test_locate_errors_in_definitions() {
+        let text = "def id = λ x . x\ndef f = λ x . x x";
+        let (program, spans) = parse_program_spanned(text).unwrap();
+        let mut session = Session::new(Ctxt::new());
+
+        let (typings, _) = session.infer(&program);
+
+        assert_eq!(typings[0].failure, None);
+        let Some((definition, _, errors)) = &typings[1].failure else {
+            panic!()
+        };
+        assert_eq!(Err(errors[0].error.clone()), typings[1].result);
+        let span = spans[1].find(&definition.expr, errors[0].expr).unwrap();
+        assert_eq!((span.start.line, span.start.column), (2, 15));
+        assert_eq!(span.text(text), "x x");
+
+        // Cached results are not inferred again, so they come without errors.
+        let (typings, _) = session.infer(&program);
+        assert!(typings[1].cached && typings[1].result.is_err() && typings[1].failure.is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::expr::Expr;
+
+/// A position in the source text, given as a byte offset and as a line and column, which count characters and start
+/// at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The source text from the start position up to, but not including, the end position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// The spans of an expression and of all its sub-expressions, in the shape of the expression: the children are the
+/// span trees of the sub-expressions, in the order given by `Expr::children`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanTree {
+    pub span: Span,
+    pub children: Vec<SpanTree>,
+}
+
+impl Position {
+    pub fn start() -> Position {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// The position after the given character at this position.
+    pub fn advance(self, c: char) -> Position {
+        if c == '\n' {
+            Position {
+                offset: self.offset + 1,
+                line: self.line + 1,
+                column: 1,
+            }
+        } else {
+            Position {
+                offset: self.offset + c.len_utf8(),
+                column: self.column + 1,
+                ..self
+            }
+        }
+    }
+
+    /// The position at the end of the text.
+    pub fn end_of(text: &str) -> Position {
+        text.chars().fold(Position::start(), Position::advance)
+    }
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+
+    /// The empty span at the position.
+    pub fn at(position: Position) -> Span {
+        Span::new(position, position)
+    }
+
+    /// The source text covered by the span.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start.offset..self.end.offset]
+    }
+}
+
+impl SpanTree {
+    pub fn new(span: Span, children: Vec<SpanTree>) -> SpanTree {
+        SpanTree { span, children }
+    }
+
+    /// The span of the sub-expression `sub` of `e`, which is the expression this tree belongs to. As in
+    /// `LocatedError`, the sub-expression is identified by its address rather than compared structurally, so that
+    /// equal sub-expressions at different places are told apart.
+    pub fn find(&self, e: &Expr, sub: &Expr) -> Option<Span> {
+        if std::ptr::eq(e, sub) {
+            return Some(self.span);
+        }
+        e.children()
+            .into_iter()
+            .zip(&self.children)
+            .find_map(|(e, tree)| tree.find(e, sub))
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_spanned;
+
+    use super::*;
+
+    #[test]
+    fn test_advance() {
+        let position = Position::end_of("λ x\n . y");
+
+        assert_eq!(
+            position,
+            Position {
+                offset: 9,
+                line: 2,
+                column: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let text = "λ f . f (f x)";
+        let (e, spans) = parse_spanned(text).unwrap();
+        let Expr::Abs(_, body) = &e else { panic!() };
+        let Expr::App(f, arg) = body.as_ref() else { panic!() };
+        let Expr::App(inner_f, _) = arg.as_ref() else { panic!() };
+
+        let text_of = |sub: &Expr| spans.find(&e, sub).map(|span| span.text(text));
+
+        assert_eq!(text_of(&e), Some(text));
+        assert_eq!(text_of(body), Some("f (f x)"));
+        assert_eq!(text_of(arg), Some("f x"));
+        // Equal sub-expressions at different places have different spans.
+        assert_eq!(spans.find(&e, f).unwrap().start.column, 7);
+        assert_eq!(spans.find(&e, inner_f).unwrap().start.column, 10);
+        assert_eq!(text_of(&Expr::var("f")), None);
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::HashSet;
+
+use crate::{
+    algorithm_j::{InferenceResult, infer_typed_with_subst},
+    ctxt::Ctxt,
+    expr::{Expr, Var},
+    typed_expr::TypedExpr,
+    types::{ARROW, Mono, Poly, TypeFunc, TypeVar},
+};
+
+/// A type of System F, where quantifiers may occur anywhere, extended by iso-recursive types.
+#[derive(Clone, Debug)]
+pub enum Type {
+    Var(TypeVar),
+    App(TypeFunc, Vec<Type>),
+    ForAll(TypeVar, Box<Type>),
+    Rec(TypeVar, Box<Type>),
+}
+
+/// A term of System F, with explicit type abstractions and type applications.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    Var(Var),
+    App(Box<Term>, Box<Term>),
+    Abs(Var, Type, Box<Term>),
+    TyAbs(TypeVar, Box<Term>),
+    TyApp(Box<Term>, Type),
+    /// Folds a term into the recursive type.
+    Fold(Type, Box<Term>),
+    /// Unfolds a term of the recursive type.
+    Unfold(Type, Box<Term>),
+    /// A syntax error, with the type inferred for it. It does not type check.
+    Error(Type),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CheckError {
+    UnknownVar(Var),
+    NotAFunction(Type),
+    ArgumentMismatch { expected: Type, found: Type },
+    NotPolymorphic(Type),
+    EscapingTypeVar(TypeVar),
+    NotRecursive(Type),
+    SyntaxError,
+}
+
+pub type CheckResult<T> = Result<T, CheckError>;
+
+/// Infers the type of an expression with Algorithm J and translates it into an explicitly typed System F term. If
+/// inference refines free type variables of the context, the term is well-typed in the refined context.
+#[allow(nonstandard_style)]
+pub fn translate(e: &Expr, Gamma: &Ctxt) -> InferenceResult<Term> {
+    let (e, S) = infer_typed_with_subst(e, Gamma)?;
+    let sigma = e.tau().clone().generalize(&Gamma.apply(&S));
+    Ok(binders(&sigma)
+        .into_iter()
+        .rev()
+        .fold(elaborate(&e), |e, alpha| Term::TyAbs(alpha, Box::new(e))))
+}
+
+/// Inserts a type application for every instantiated variable occurrence and a type abstraction for every type
+/// variable generalized at a `let` expression.
+pub fn elaborate(e: &TypedExpr) -> Term {
+    match e {
+        TypedExpr::Var(x, inst, _) => {
+            let mut alphas: Vec<&TypeVar> = inst.keys().collect();
+            alphas.sort();
+            alphas.into_iter().fold(Term::Var(x.clone()), |e, alpha| {
+                Term::TyApp(Box::new(e), Type::from(&inst[alpha]))
+            })
+        }
+        TypedExpr::App(e0, e1, _) => Term::App(Box::new(elaborate(e0)), Box::new(elaborate(e1))),
+        TypedExpr::Abs(x, e, tau) => {
+            let Mono::App(_, taus) = tau else {
+                unreachable!("abstractions have function types")
+            };
+            Term::Abs(x.clone(), Type::from(&taus[0]), Box::new(elaborate(e)))
+        }
+        TypedExpr::Let(x, sigma, e0, e1, _) => {
+            let e0 = binders(sigma)
+                .into_iter()
+                .rev()
+                .fold(elaborate(e0), |e, alpha| Term::TyAbs(alpha, Box::new(e)));
+            let e1 = Term::Abs(x.clone(), Type::from(sigma), Box::new(elaborate(e1)));
+            Term::App(Box::new(e1), Box::new(e0))
+        }
+        TypedExpr::Fold(_, e, tau) => Term::Fold(Type::from(tau), Box::new(elaborate(e))),
+        TypedExpr::Unfold(_, e, _) => Term::Unfold(Type::from(e.tau()), Box::new(elaborate(e))),
+        TypedExpr::Error(tau) => Term::Error(Type::from(tau)),
+    }
+}
+
+/// The bound variables of a type scheme, in the order in which they are quantified over in System F.
+fn binders(sigma: &Poly) -> Vec<TypeVar> {
+    let Poly(alphas, _) = sigma;
+    let mut alphas: Vec<TypeVar> = alphas.iter().cloned().collect();
+    alphas.sort();
+    alphas
+}
+
+/// Type checks a System F term. This does not rely on any part of the Hindley-Milner inference, so it can be used
+/// to validate its results.
+#[allow(nonstandard_style)]
+pub fn check(e: &Term, Gamma: &Ctxt) -> CheckResult<Type> {
+    Checker {
+        Gamma,
+        scope: Vec::new(),
+    }
+    .check(e)
+}
+
+#[allow(nonstandard_style)]
+struct Checker<'a> {
+    Gamma: &'a Ctxt,
+    scope: Vec<(Var, Type)>,
+}
+
+impl Checker<'_> {
+    #[allow(nonstandard_style)]
+    fn check(&mut self, e: &Term) -> CheckResult<Type> {
+        match e {
+            Term::Var(x) => match self.scope.iter().rev().find(|(y, _)| x == y) {
+                Some((_, tau)) => Ok(tau.clone()),
+                None => self
+                    .Gamma
+                    .get(x)
+                    .map(Type::from)
+                    .ok_or_else(|| CheckError::UnknownVar(x.clone())),
+            },
+            Term::App(e0, e1) => {
+                let tau0 = self.check(e0)?;
+                let tau1 = self.check(e1)?;
+                match tau0 {
+                    Type::App(C, mut taus) if C == ARROW && taus.len() == 2 => {
+                        let tau_prime = taus.pop().unwrap();
+                        let tau = taus.pop().unwrap();
+                        if tau == tau1 {
+                            Ok(tau_prime)
+                        } else {
+                            Err(CheckError::ArgumentMismatch {
+                                expected: tau,
+                                found: tau1,
+                            })
+                        }
+                    }
+                    tau0 => Err(CheckError::NotAFunction(tau0)),
+                }
+            }
+            Term::Abs(x, tau, e) => {
+                self.scope.push((x.clone(), tau.clone()));
+                let tau_prime = self.check(e);
+                self.scope.pop();
+                Ok(Type::arrow(tau.clone(), tau_prime?))
+            }
+            Term::TyAbs(alpha, e) => {
+                let free_in_scope = self.scope.iter().any(|(_, tau)| tau.free().contains(alpha));
+                if free_in_scope || self.Gamma.free().contains(alpha) {
+                    return Err(CheckError::EscapingTypeVar(alpha.clone()));
+                }
+                let tau = self.check(e)?;
+                Ok(Type::ForAll(alpha.clone(), Box::new(tau)))
+            }
+            Term::TyApp(e, tau) => match self.check(e)? {
+                Type::ForAll(alpha, tau_prime) => Ok(tau_prime.replace(&alpha, tau)),
+                tau_prime => Err(CheckError::NotPolymorphic(tau_prime)),
+            },
+            Term::Fold(tau, e) => {
+                if !matches!(tau, Type::Rec(..)) {
+                    return Err(CheckError::NotRecursive(tau.clone()));
+                }
+                let expected = tau.clone().unfold();
+                let found = self.check(e)?;
+                if expected == found {
+                    Ok(tau.clone())
+                } else {
+                    Err(CheckError::ArgumentMismatch { expected, found })
+                }
+            }
+            Term::Unfold(tau, e) => {
+                if !matches!(tau, Type::Rec(..)) {
+                    return Err(CheckError::NotRecursive(tau.clone()));
+                }
+                let found = self.check(e)?;
+                if *tau == found {
+                    Ok(tau.clone().unfold())
+                } else {
+                    Err(CheckError::ArgumentMismatch {
+                        expected: tau.clone(),
+                        found,
+                    })
+                }
+            }
+            Term::Error(_) => Err(CheckError::SyntaxError),
+        }
+    }
+}
+
+impl Type {
+    pub fn arrow(tau1: Type, tau2: Type) -> Type {
+        Type::App(String::from(ARROW), vec![tau1, tau2])
+    }
+
+    pub fn free(&self) -> HashSet<TypeVar> {
+        match self {
+            Type::Var(alpha) => HashSet::from([alpha.clone()]),
+            Type::App(_, taus) => taus.iter().flat_map(|tau| tau.free()).collect(),
+            Type::ForAll(alpha, tau) | Type::Rec(alpha, tau) => {
+                let mut alphas = tau.free();
+                alphas.remove(alpha);
+                alphas
+            }
+        }
+    }
+
+    /// Replaces the bound variable of a recursive type by the recursive type itself.
+    pub fn unfold(self) -> Type {
+        match self {
+            Type::Rec(ref alpha, ref tau) => tau.as_ref().clone().replace(alpha, &self),
+            tau => tau,
+        }
+    }
+
+    /// Substitutes `beta` for `alpha`, renaming bound variables where necessary to avoid capturing free variables
+    /// of `beta`.
+    #[allow(nonstandard_style)]
+    pub fn replace(self, alpha: &str, beta: &Type) -> Type {
+        match self {
+            Type::Var(gamma) if gamma == alpha => beta.clone(),
+            Type::Var(_) => self,
+            Type::App(C, taus) => Type::App(C, taus.into_iter().map(|tau| tau.replace(alpha, beta)).collect()),
+            Type::ForAll(ref gamma, _) if gamma == alpha => self,
+            Type::ForAll(gamma, tau) if beta.free().contains(&gamma) => {
+                let mut delta = format!("{gamma}'");
+                while beta.free().contains(&delta) || tau.free().contains(&delta) {
+                    delta.push('\'');
+                }
+                let tau = tau.replace(&gamma, &Type::Var(delta.clone()));
+                Type::ForAll(delta, Box::new(tau.replace(alpha, beta)))
+            }
+            Type::ForAll(gamma, tau) => Type::ForAll(gamma, Box::new(tau.replace(alpha, beta))),
+            Type::Rec(gamma, tau) => {
+                let Type::ForAll(gamma, tau) = Type::ForAll(gamma, tau).replace(alpha, beta) else {
+                    unreachable!()
+                };
+                Type::Rec(gamma, tau)
+            }
+        }
+    }
+
+    #[allow(nonstandard_style)]
+    fn is_arrow(&self) -> bool {
+        matches!(self, Type::App(C, _) if C == ARROW)
+    }
+
+    #[allow(nonstandard_style)]
+    fn alpha_eq<'a>(&'a self, other: &'a Type, bound: &mut Vec<(&'a TypeVar, &'a TypeVar)>) -> bool {
+        match (self, other) {
+            (Type::Var(alpha1), Type::Var(alpha2)) => {
+                match bound
+                    .iter()
+                    .rev()
+                    .find(|(beta1, beta2)| beta1 == &alpha1 || beta2 == &alpha2)
+                {
+                    Some((beta1, beta2)) => beta1 == &alpha1 && beta2 == &alpha2,
+                    None => alpha1 == alpha2,
+                }
+            }
+            (Type::App(C1, taus1), Type::App(C2, taus2)) if C1 == C2 && taus1.len() == taus2.len() => {
+                taus1.iter().zip(taus2).all(|(tau1, tau2)| tau1.alpha_eq(tau2, bound))
+            }
+            (Type::ForAll(alpha1, tau1), Type::ForAll(alpha2, tau2))
+            | (Type::Rec(alpha1, tau1), Type::Rec(alpha2, tau2)) => {
+                bound.push((alpha1, alpha2));
+                let result = tau1.alpha_eq(tau2, bound);
+                bound.pop();
+                result
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Types are equal if they are equal up to renaming of bound variables.
+impl PartialEq for Type {
+    fn eq(&self, other: &Self) -> bool {
+        self.alpha_eq(other, &mut Vec::new())
+    }
+}
+
+impl Eq for Type {}
+
+impl From<&Mono> for Type {
+    #[allow(nonstandard_style)]
+    fn from(tau: &Mono) -> Type {
+        match tau {
+            Mono::Var(alpha) => Type::Var(alpha.clone()),
+            Mono::App(C, taus) => Type::App(C.clone(), taus.iter().map(Type::from).collect()),
+            Mono::Rec(alpha, tau) => Type::Rec(alpha.clone(), Box::new(Type::from(tau.as_ref()))),
+        }
+    }
+}
+
+impl From<&Poly> for Type {
+    fn from(sigma: &Poly) -> Type {
+        let Poly(_, tau) = sigma;
+        binders(sigma)
+            .into_iter()
+            .rev()
+            .fold(Type::from(tau), |tau, alpha| Type::ForAll(alpha, Box::new(tau)))
+    }
+}
+
+impl std::fmt::Display for Type {
+    #[allow(nonstandard_style)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Var(alpha) => write!(f, "{}", alpha),
+            Type::App(C, taus) if C == ARROW => {
+                let [tau1, tau2] = &taus[..] else { panic!() };
+
+                if tau1.is_arrow() || matches!(tau1, Type::ForAll(..) | Type::Rec(..)) {
+                    write!(f, "({})", tau1)?
+                } else {
+                    write!(f, "{}", tau1)?
+                }
+
+                write!(f, " → {}", tau2)
+            }
+            Type::App(C, taus) => {
+                write!(f, "{}", C)?;
+                for tau in taus {
+                    if matches!(tau, Type::Var(..)) || matches!(tau, Type::App(_, taus) if taus.is_empty()) {
+                        write!(f, " {}", tau)?
+                    } else {
+                        write!(f, " ({})", tau)?
+                    }
+                }
+                Ok(())
+            }
+            Type::ForAll(alpha, tau) => write!(f, "∀{} . {}", alpha, tau),
+            Type::Rec(alpha, tau) => write!(f, "μ{} . {}", alpha, tau),
+        }
+    }
+}
+
+impl std::fmt::Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Term::Var(x) => write!(f, "{}", x),
+            Term::App(e1, e2) => {
+                if matches!(**e1, Term::Abs(..) | Term::TyAbs(..)) {
+                    write!(f, "({})", e1)?
+                } else {
+                    write!(f, "{}", e1)?
+                }
+
+                if matches!(**e2, Term::Var(..)) {
+                    write!(f, " {}", e2)
+                } else {
+                    write!(f, " ({})", e2)
+                }
+            }
+            Term::Abs(x, tau, e) => write!(f, "λ{} : {} . {}", x, tau, e),
+            Term::TyAbs(alpha, e) => write!(f, "Λ{} . {}", alpha, e),
+            Term::TyApp(e, tau) => {
+                if matches!(**e, Term::Abs(..) | Term::TyAbs(..)) {
+                    write!(f, "({}) [{}]", e, tau)
+                } else {
+                    write!(f, "{} [{}]", e, tau)
+                }
+            }
+            Term::Fold(tau, e) | Term::Unfold(tau, e) => {
+                let keyword = if matches!(self, Term::Fold(..)) {
+                    "fold"
+                } else {
+                    "unfold"
+                };
+                if matches!(**e, Term::Var(..)) {
+                    write!(f, "{} [{}] {}", keyword, tau, e)
+                } else {
+                    write!(f, "{} [{}] ({})", keyword, tau, e)
+                }
+            }
+            Term::Error(tau) => write!(f, "(? : {})", tau),
+        }
+    }
+}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckError::UnknownVar(x) => write!(f, "Unknown variable '{x}'"),
+            CheckError::NotAFunction(tau) => write!(f, "Applied term of type '{tau}' is not a function"),
+            CheckError::ArgumentMismatch { expected, found } => {
+                write!(f, "Expected argument of type '{expected}', found '{found}'")
+            }
+            CheckError::NotPolymorphic(tau) => write!(f, "Type-applied term of type '{tau}' is not polymorphic"),
+            CheckError::EscapingTypeVar(alpha) => {
+                write!(
+                    f,
+                    "Cannot abstract over type variable '{alpha}' which is free in the context"
+                )
+            }
+            CheckError::NotRecursive(tau) => write!(f, "Cannot fold or unfold type '{tau}' which is not recursive"),
+            CheckError::SyntaxError => write!(f, "Cannot type check a syntax error"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm_j::{infer_with_subst, tests::cases},
+        parse::{parse, parse_poly},
+    };
+
+    use super::*;
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_translation_type_checks() {
+        for (Gamma, e, _) in cases() {
+            let Ok((sigma, S)) = infer_with_subst(&e, &Gamma) else {
+                continue;
+            };
+
+            let e = translate(&e, &Gamma).unwrap();
+
+            assert_eq!(check(&e, &Gamma.apply(&S)), Ok(Type::from(&sigma)), "checking {e}");
+        }
+    }
+
+    #[test]
+    fn test_translate_let() {
+        let e = translate(&parse("let id = λ x . x in id id").unwrap(), &Ctxt::new()).unwrap();
+
+        assert_eq!(
+            e.to_string(),
+            "Λ_3 . (λid : ∀_1 . _1 → _1 . id [_3 → _3] (id [_3])) (Λ_1 . λx : _1 . x)"
+        );
+    }
+
+    #[test]
+    fn test_translate_top_level() {
+        let e = translate(&parse("λ x . x").unwrap(), &Ctxt::new()).unwrap();
+
+        assert_eq!(e.to_string(), "Λ_1 . λx : _1 . x");
+        assert_eq!(check(&e, &Ctxt::new()).unwrap().to_string(), "∀_1 . _1 → _1");
+    }
+
+    #[test]
+    #[allow(nonstandard_style)]
+    fn test_check_rejects_ill_typed_terms() {
+        let Gamma = Ctxt::new().bind("n", parse_poly("Int").unwrap());
+        let Int = Type::from(&parse_poly("Int").unwrap().1);
+        let id = Term::TyAbs(
+            "a".into(),
+            Box::new(Term::Abs(
+                "x".into(),
+                Type::Var("a".into()),
+                Box::new(Term::Var("x".into())),
+            )),
+        );
+
+        let applied = Term::App(Box::new(id.clone()), Box::new(Term::Var("n".into())));
+        assert!(matches!(check(&applied, &Gamma), Err(CheckError::NotAFunction(..))));
+
+        let instantiated = Term::TyApp(Box::new(id.clone()), Int.clone());
+        let applied = Term::App(Box::new(instantiated), Box::new(Term::Var("n".into())));
+        assert_eq!(check(&applied, &Gamma), Ok(Int.clone()));
+
+        let Bool = Type::from(&parse_poly("Bool").unwrap().1);
+        let instantiated = Term::TyApp(Box::new(id), Bool.clone());
+        let applied = Term::App(Box::new(instantiated), Box::new(Term::Var("n".into())));
+        assert_eq!(
+            check(&applied, &Gamma),
+            Err(CheckError::ArgumentMismatch {
+                expected: Bool,
+                found: Int
+            })
+        );
+    }
+
+    #[test]
+    fn test_replace_avoids_capture() {
+        let tau = Type::ForAll(
+            "b".into(),
+            Box::new(Type::arrow(Type::Var("a".into()), Type::Var("b".into()))),
+        );
+
+        let tau = tau.replace("a", &Type::Var("b".into()));
+
+        assert_eq!(tau.to_string(), "∀b' . b → b'");
+    }
+}
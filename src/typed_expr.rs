@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use crate::{
+    expr::{Expr, Var},
+    types::{Mono, Poly, Subst, TypeVar},
+};
+
+/// An expression in which every node carries its type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypedExpr {
+    /// A variable occurrence, together with the substitution instantiating the bound variables of its type scheme.
+    Var(Var, Subst, Mono),
+    App(Box<TypedExpr>, Box<TypedExpr>, Mono),
+    Abs(Var, Box<TypedExpr>, Mono),
+    /// A `let` expression, together with the generalized type of the bound variable.
+    Let(Var, Poly, Box<TypedExpr>, Box<TypedExpr>, Mono),
+    /// A `fold` or `unfold`, together with its annotation as written. The type of the folded, respectively unfolded,
+    /// expression is an instance of the annotation.
+    Fold(Mono, Box<TypedExpr>, Mono),
+    Unfold(Mono, Box<TypedExpr>, Mono),
+    /// A syntax error, with the fresh type it was given.
+    Error(Mono),
+}
+
+impl TypedExpr {
+    pub fn app(e1: TypedExpr, e2: TypedExpr, tau: Mono) -> TypedExpr {
+        TypedExpr::App(Box::new(e1), Box::new(e2), tau)
+    }
+
+    pub fn abs(x: impl Into<Var>, e: TypedExpr, tau: Mono) -> TypedExpr {
+        TypedExpr::Abs(x.into(), Box::new(e), tau)
+    }
+
+    pub fn r#let(x: impl Into<Var>, sigma: Poly, e1: TypedExpr, e2: TypedExpr, tau: Mono) -> TypedExpr {
+        TypedExpr::Let(x.into(), sigma, Box::new(e1), Box::new(e2), tau)
+    }
+
+    pub fn fold(tau_rec: Mono, e: TypedExpr, tau: Mono) -> TypedExpr {
+        TypedExpr::Fold(tau_rec, Box::new(e), tau)
+    }
+
+    pub fn unfold(tau_rec: Mono, e: TypedExpr, tau: Mono) -> TypedExpr {
+        TypedExpr::Unfold(tau_rec, Box::new(e), tau)
+    }
+
+    pub fn tau(&self) -> &Mono {
+        match self {
+            TypedExpr::Var(.., tau)
+            | TypedExpr::App(.., tau)
+            | TypedExpr::Abs(.., tau)
+            | TypedExpr::Let(.., tau)
+            | TypedExpr::Fold(.., tau)
+            | TypedExpr::Unfold(.., tau)
+            | TypedExpr::Error(tau) => tau,
+        }
+    }
+
+    /// The immediate sub-expressions, from left to right, as in `Expr::children`.
+    pub fn children(&self) -> Vec<&TypedExpr> {
+        match self {
+            TypedExpr::Var(..) | TypedExpr::Error(..) => Vec::new(),
+            TypedExpr::App(e1, e2, _) | TypedExpr::Let(_, _, e1, e2, _) => vec![e1, e2],
+            TypedExpr::Abs(_, e, _) | TypedExpr::Fold(_, e, _) | TypedExpr::Unfold(_, e, _) => vec![e],
+        }
+    }
+
+    /// The underlying expression without types.
+    pub fn erase(&self) -> Expr {
+        match self {
+            TypedExpr::Var(x, ..) => Expr::var(x),
+            TypedExpr::App(e1, e2, _) => Expr::app(e1.erase(), e2.erase()),
+            TypedExpr::Abs(x, e, _) => Expr::abs(x, e.erase()),
+            TypedExpr::Let(x, _, e1, e2, _) => Expr::r#let(x, e1.erase(), e2.erase()),
+            TypedExpr::Fold(tau_rec, e, _) => Expr::fold(tau_rec.clone(), e.erase()),
+            TypedExpr::Unfold(tau_rec, e, _) => Expr::unfold(tau_rec.clone(), e.erase()),
+            TypedExpr::Error(_) => Expr::Error,
+        }
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn canonicalize(self, aliases: &HashMap<TypeVar, Mono>) -> TypedExpr {
+        match self {
+            TypedExpr::Var(x, S, tau) => TypedExpr::Var(
+                x,
+                S.into_iter()
+                    .map(|(alpha, tau)| (alpha, tau.canonicalize(aliases)))
+                    .collect(),
+                tau.canonicalize(aliases),
+            ),
+            TypedExpr::App(e1, e2, tau) => TypedExpr::app(
+                e1.canonicalize(aliases),
+                e2.canonicalize(aliases),
+                tau.canonicalize(aliases),
+            ),
+            TypedExpr::Abs(x, e, tau) => TypedExpr::abs(x, e.canonicalize(aliases), tau.canonicalize(aliases)),
+            TypedExpr::Let(x, Poly(alphas, tau_x), e1, e2, tau) => TypedExpr::r#let(
+                x,
+                Poly(alphas, tau_x.canonicalize(aliases)),
+                e1.canonicalize(aliases),
+                e2.canonicalize(aliases),
+                tau.canonicalize(aliases),
+            ),
+            TypedExpr::Fold(tau_rec, e, tau) => {
+                TypedExpr::fold(tau_rec, e.canonicalize(aliases), tau.canonicalize(aliases))
+            }
+            TypedExpr::Unfold(tau_rec, e, tau) => {
+                TypedExpr::unfold(tau_rec, e.canonicalize(aliases), tau.canonicalize(aliases))
+            }
+            TypedExpr::Error(tau) => TypedExpr::Error(tau.canonicalize(aliases)),
+        }
+    }
+}
+
+impl std::fmt::Display for TypedExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypedExpr::Var(x, _, tau) => write!(f, "({} : {})", x, tau),
+            TypedExpr::App(e1, e2, tau) => write!(f, "({} {} : {})", e1, e2, tau),
+            TypedExpr::Abs(x, e, tau) => write!(f, "(λ{} . {} : {})", x, e, tau),
+            TypedExpr::Let(x, sigma, e1, e2, tau) => write!(f, "(let {} : {} = {} in {} : {})", x, sigma, e1, e2, tau),
+            TypedExpr::Fold(tau_rec, e, tau) => write!(f, "(fold [{}] {} : {})", tau_rec, e, tau),
+            TypedExpr::Unfold(tau_rec, e, tau) => write!(f, "(unfold [{}] {} : {})", tau_rec, e, tau),
+            TypedExpr::Error(tau) => write!(f, "(? : {})", tau),
+        }
+    }
+}
@@ -4,17 +4,21 @@ use crate::ctxt::Ctxt;
 
 pub type TypeVar = String;
 pub type TypeFunc = String;
+pub type Subst = HashMap<TypeVar, Mono>;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Mono {
     Var(TypeVar),
     App(TypeFunc, Vec<Mono>),
+    /// The recursive type `μα . τ`, which is equal to its unfolding `τ[α := μα . τ]`.
+    Rec(TypeVar, Box<Mono>),
 }
 
 #[derive(Clone, Debug, Eq)]
 pub struct Poly(pub HashSet<TypeVar>, pub Mono);
 
 pub const ARROW: &str = "→";
+pub const MU: &str = "μ";
 
 impl Mono {
     pub fn arrow(tau1: Mono, tau2: Mono) -> Mono {
@@ -22,7 +26,6 @@ impl Mono {
     }
 
     #[allow(nonstandard_style)]
-    #[cfg(test)]
     pub fn nullary(C: impl Into<TypeFunc>) -> Mono {
         Mono::App(C.into(), Vec::new())
     }
@@ -31,6 +34,11 @@ impl Mono {
         match self {
             Mono::Var(alpha) => HashSet::from([alpha.clone()]),
             Mono::App(_, taus) => taus.iter().flat_map(|tau| tau.free()).collect(),
+            Mono::Rec(alpha, tau) => {
+                let mut free = tau.free();
+                free.remove(alpha);
+                free
+            }
         }
     }
 
@@ -40,14 +48,58 @@ impl Mono {
         Poly(alphas, self)
     }
 
-    #[allow(nonstandard_style)]
+    /// Replaces all aliased type variables by their types. A variable whose alias refers back to the variable itself
+    /// is turned into a recursive type.
     pub fn canonicalize(self, aliases: &HashMap<TypeVar, Mono>) -> Mono {
+        self.canonicalize_avoiding(aliases, &mut Vec::new())
+    }
+
+    /// Canonicalizes, but leaves the variables in `visiting`, which are being expanded or bound by `μ`, unchanged.
+    #[allow(nonstandard_style)]
+    fn canonicalize_avoiding(self, aliases: &HashMap<TypeVar, Mono>, visiting: &mut Vec<TypeVar>) -> Mono {
+        match self {
+            Mono::Var(ref alpha) if visiting.contains(alpha) => self,
+            Mono::Var(alpha) => match aliases.get(&alpha) {
+                Some(tau) => {
+                    visiting.push(alpha.clone());
+                    let tau = tau.clone().canonicalize_avoiding(aliases, visiting);
+                    visiting.pop();
+                    if tau.occurs(&alpha) {
+                        Mono::Rec(alpha, Box::new(tau))
+                    } else {
+                        tau
+                    }
+                }
+                None => Mono::Var(alpha),
+            },
+            Mono::App(C, taus) => Mono::App(
+                C,
+                taus.into_iter()
+                    .map(|tau| tau.canonicalize_avoiding(aliases, visiting))
+                    .collect(),
+            ),
+            Mono::Rec(alpha, tau) => {
+                visiting.push(alpha.clone());
+                let tau = tau.canonicalize_avoiding(aliases, visiting);
+                visiting.pop();
+                Mono::Rec(alpha, Box::new(tau))
+            }
+        }
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn apply(self, S: &Subst) -> Mono {
         match self {
-            Mono::Var(ref alpha) => match aliases.get(alpha) {
-                Some(tau) => tau.clone().canonicalize(aliases),
+            Mono::Var(ref alpha) => match S.get(alpha) {
+                Some(tau) => tau.clone(),
                 None => self,
             },
-            Mono::App(C, taus) => Mono::App(C, taus.into_iter().map(|tau| tau.canonicalize(aliases)).collect()),
+            Mono::App(C, taus) => Mono::App(C, taus.into_iter().map(|tau| tau.apply(S)).collect()),
+            Mono::Rec(alpha, tau) => {
+                let mut S = S.clone();
+                S.remove(&alpha);
+                Mono::Rec(alpha, Box::new(tau.apply(&S)))
+            }
         }
     }
 
@@ -57,6 +109,89 @@ impl Mono {
             Mono::Var(gamma) if gamma == alpha => beta.clone(),
             Mono::Var(_) => self,
             Mono::App(C, taus) => Mono::App(C, taus.into_iter().map(|tau| tau.replace(alpha, beta)).collect()),
+            Mono::Rec(ref gamma, _) if gamma == alpha => self,
+            Mono::Rec(gamma, tau) => Mono::Rec(gamma, Box::new(tau.replace(alpha, beta))),
+        }
+    }
+
+    /// The subterm reached by following the path of type argument indices, as far as the path leads into type
+    /// applications.
+    pub fn at(&self, path: &[usize]) -> &Mono {
+        match (self, path) {
+            (Mono::App(_, taus), [i, rest @ ..]) => taus[*i].at(rest),
+            _ => self,
+        }
+    }
+
+    /// Replaces a recursive type `μα . τ` by `τ[α := μα . τ]`, and leaves other types unchanged.
+    pub fn unfold(self) -> Mono {
+        match self {
+            Mono::Rec(ref alpha, ref tau) => tau.as_ref().clone().replace(alpha, &self),
+            tau => tau,
+        }
+    }
+
+    /// Replaces the free type variables by fresh ones, as for the annotation of a `fold` or `unfold`.
+    pub fn instantiate(&self, mut new_var: impl FnMut() -> Mono) -> Mono {
+        self.clone()
+            .generalize(&Ctxt::new())
+            .inst(std::iter::from_fn(|| Some(new_var())))
+    }
+
+    /// Whether the two types are equal up to renaming the variables bound by `μ`.
+    pub fn alpha_equivalent(&self, other: &Mono) -> bool {
+        Poly::mono(self.clone()) == Poly::mono(other.clone())
+    }
+
+    /// Whether the two types are equal when recursive types are identified with their unfoldings, i.e., whether they
+    /// denote the same infinite tree.
+    pub fn equivalent(&self, other: &Mono) -> bool {
+        self.equivalent_assuming(other, &mut Vec::new())
+    }
+
+    #[allow(nonstandard_style)]
+    fn equivalent_assuming(&self, other: &Mono, assumed: &mut Vec<(Mono, Mono)>) -> bool {
+        let pair = (self.clone(), other.clone());
+        if assumed.contains(&pair) {
+            return true;
+        }
+        match (self, other) {
+            (Mono::Rec(..), _) | (_, Mono::Rec(..)) => {
+                assumed.push(pair);
+                self.clone()
+                    .unfold()
+                    .equivalent_assuming(&other.clone().unfold(), assumed)
+            }
+            (Mono::Var(alpha), Mono::Var(beta)) => alpha == beta,
+            (Mono::App(C1, taus1), Mono::App(C2, taus2)) if C1 == C2 && taus1.len() == taus2.len() => {
+                assumed.push(pair);
+                taus1
+                    .iter()
+                    .zip(taus2)
+                    .all(|(tau1, tau2)| tau1.equivalent_assuming(tau2, assumed))
+            }
+            _ => false,
+        }
+    }
+
+    /// The type variables in order of their first occurrence.
+    fn vars(&self) -> Vec<&TypeVar> {
+        let mut vars = Vec::new();
+        self.collect_vars(&mut vars);
+        vars
+    }
+
+    fn collect_vars<'a>(&'a self, vars: &mut Vec<&'a TypeVar>) {
+        match self {
+            Mono::Var(alpha) if !vars.contains(&alpha) => vars.push(alpha),
+            Mono::Var(_) => (),
+            Mono::App(_, taus) => taus.iter().for_each(|tau| tau.collect_vars(vars)),
+            Mono::Rec(alpha, tau) => {
+                if !vars.contains(&alpha) {
+                    vars.push(alpha);
+                }
+                tau.collect_vars(vars)
+            }
         }
     }
 
@@ -64,6 +199,33 @@ impl Mono {
         match self {
             Mono::Var(beta) => alpha == beta,
             Mono::App(_, taus) => taus.iter().any(|tau| tau.occurs(alpha)),
+            Mono::Rec(beta, tau) => alpha != beta && tau.occurs(alpha),
+        }
+    }
+
+    /// Extends `S` such that applying it to `self`, which may only replace the variables `alphas`, yields `tau`.
+    #[allow(nonstandard_style)]
+    fn matches(&self, tau: &Mono, alphas: &HashSet<TypeVar>, S: &mut Subst) -> bool {
+        match (self, tau) {
+            (Mono::Var(alpha), tau) if alphas.contains(alpha) => match S.get(alpha) {
+                Some(tau_prime) => tau_prime == tau,
+                None => {
+                    S.insert(alpha.clone(), tau.clone());
+                    true
+                }
+            },
+            (Mono::Var(alpha), Mono::Var(beta)) => alpha == beta,
+            (Mono::App(C1, taus1), Mono::App(C2, taus2)) if C1 == C2 && taus1.len() == taus2.len() => taus1
+                .iter()
+                .zip(taus2)
+                .all(|(tau1, tau2)| tau1.matches(tau2, alphas, S)),
+            (Mono::Rec(alpha, tau1), Mono::Rec(beta, tau2)) => {
+                let taken = |gamma: &TypeVar| alphas.contains(gamma) || S.values().any(|tau| tau.occurs(gamma));
+                let (gamma, tau1, tau2) = open_recursive(alpha, *tau1.clone(), beta, *tau2.clone(), taken);
+                // The common bound variable must not escape into the substitution.
+                tau1.matches(&tau2, alphas, S) && !S.values().any(|tau| tau.occurs(&gamma))
+            }
+            _ => false,
         }
     }
 
@@ -71,6 +233,25 @@ impl Mono {
     fn is_arrow(&self) -> bool {
         matches!(self, Mono::App(C, _) if C == ARROW)
     }
+
+    /// Renames the variables bound by `μ` to the given names, which must not occur in the type.
+    #[allow(nonstandard_style)]
+    fn rename_recursive(self, names: &mut impl Iterator<Item = TypeVar>) -> Mono {
+        match self {
+            Mono::Var(_) => self,
+            Mono::App(C, taus) => Mono::App(C, taus.into_iter().map(|tau| tau.rename_recursive(names)).collect()),
+            Mono::Rec(alpha, tau) => {
+                let beta = names.next().unwrap();
+                let tau = tau.replace(&alpha, &Mono::Var(beta.clone()));
+                Mono::Rec(beta, Box::new(tau.rename_recursive(names)))
+            }
+        }
+    }
+
+    /// Whether the type needs parentheses on the left of an arrow or as an argument of a type function.
+    fn extends_right(&self) -> bool {
+        self.is_arrow() || matches!(self, Mono::Rec(..))
+    }
 }
 
 impl Poly {
@@ -80,16 +261,124 @@ impl Poly {
 
     pub fn free(&self) -> HashSet<TypeVar> {
         let Poly(alphas, tau) = self;
-        &tau.free() - &alphas
+        &tau.free() - alphas
     }
 
-    pub fn inst(self, new_vars: impl IntoIterator<Item = Mono>) -> Mono {
+    #[allow(nonstandard_style)]
+    pub fn apply(self, S: &Subst) -> Poly {
+        let Poly(alphas, tau) = self;
+        let S = S
+            .iter()
+            .filter(|(alpha, _)| !alphas.contains(*alpha))
+            .map(|(alpha, tau)| (alpha.clone(), tau.clone()))
+            .collect();
+        let tau = tau.apply(&S);
+        Poly(alphas, tau)
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn canonicalize(self, aliases: &HashMap<TypeVar, Mono>) -> Poly {
+        let Poly(alphas, tau) = self;
+        let aliases = aliases
+            .iter()
+            .filter(|(alpha, _)| !alphas.contains(*alpha))
+            .map(|(alpha, tau)| (alpha.clone(), tau.clone()))
+            .collect();
+        let tau = tau.canonicalize(&aliases);
+        Poly(alphas, tau)
+    }
+
+    pub fn as_mono(&self) -> Option<&Mono> {
+        let Poly(alphas, tau) = self;
+        alphas.is_empty().then_some(tau)
+    }
+
+    /// Whether `other` is a specialization of `self`, written `self ⊑ other`.
+    #[allow(nonstandard_style)]
+    pub fn is_more_general_than(&self, other: &Poly) -> bool {
+        let Poly(alphas, tau) = self;
+        let Poly(betas, tau_prime) = other;
+        let free = self.free();
+        tau.matches(tau_prime, alphas, &mut Subst::new()) && betas.iter().all(|beta| !free.contains(beta))
+    }
+
+    /// The substitution replacing the bound variables, in the order in which they are printed, by the given types.
+    pub fn instantiation(&self, new_vars: impl IntoIterator<Item = Mono>) -> Subst {
+        self.bound_vars().into_iter().cloned().zip(new_vars).collect()
+    }
+
+    /// The bound variables in order of their first occurrence, followed by those not occurring at all, in sorted order.
+    pub fn bound_vars(&self) -> Vec<&TypeVar> {
         let Poly(alphas, tau) = self;
-        alphas
+        let mut bound: Vec<&TypeVar> = tau.vars().into_iter().filter(|alpha| alphas.contains(*alpha)).collect();
+        let mut unused: Vec<&TypeVar> = alphas.iter().filter(|alpha| !bound.contains(alpha)).collect();
+        unused.sort();
+        bound.extend(unused);
+        bound
+    }
+
+    /// Renames the bound variables, followed by the variables bound by `μ`, to `a`, `b`, `c`, … in order of their
+    /// first occurrence, skipping the names of free variables.
+    pub fn normalize(self) -> Poly {
+        let free = self.free();
+        let mut names = (0..)
+            .flat_map(|i| ('a'..='z').map(move |c| if i == 0 { c.to_string() } else { format!("{c}{i}") }))
+            .filter(|name| !free.contains(name));
+        let renaming: Subst = self
+            .bound_vars()
             .into_iter()
-            .zip(new_vars)
-            .fold(tau, |tau, (alpha, beta)| tau.replace(&alpha, &beta))
+            .map(|alpha| (alpha.clone(), Mono::Var(names.next().unwrap())))
+            .collect();
+
+        let Poly(_, tau) = self;
+        // The variables bound by `μ` are renamed out of the way first, so that they cannot capture the new names.
+        let taken: HashSet<TypeVar> = tau.vars().into_iter().cloned().collect();
+        let mut placeholders = (0..).map(|i| format!("μ{i}")).filter(|name| !taken.contains(name));
+        let tau = tau.rename_recursive(&mut placeholders);
+        let alphas = renaming.values().flat_map(Mono::free).collect();
+        Poly(alphas, tau.apply(&renaming).rename_recursive(&mut names))
+    }
+
+    #[allow(nonstandard_style)]
+    pub fn inst(self, new_vars: impl IntoIterator<Item = Mono>) -> Mono {
+        let S = self.instantiation(new_vars);
+        let Poly(_, tau) = self;
+        tau.apply(&S)
+    }
+}
+
+/// Renames the variables bound by the recursive types `μα . τ1` and `μβ . τ2` to a common variable, and returns it
+/// together with the renamed bodies `τ1` and `τ2`. The common variable occurs nowhere else in the bodies and is not
+/// `taken`, so the recursive types are equal if and only if the bodies can be unified without binding it.
+pub fn open_recursive(
+    alpha: &str,
+    tau1: Mono,
+    beta: &str,
+    tau2: Mono,
+    taken: impl Fn(&TypeVar) -> bool,
+) -> (TypeVar, Mono, Mono) {
+    let mut gamma = alpha.to_string();
+    let vars: HashSet<TypeVar> = tau1.vars().into_iter().chain(tau2.vars()).cloned().collect();
+    // If both bound variables are the same, it may stay, as it only occurs bound in the bodies.
+    while ((gamma != alpha || alpha != beta) && vars.contains(&gamma)) || taken(&gamma) {
+        gamma.push('\'');
+    }
+    let tau1 = tau1.replace(alpha, &Mono::Var(gamma.clone()));
+    let tau2 = tau2.replace(beta, &Mono::Var(gamma.clone()));
+    (gamma, tau1, tau2)
+}
+
+/// Composes two substitutions such that applying the result is the same as applying `S1` and then `S2`.
+#[allow(nonstandard_style)]
+pub fn compose(S2: &Subst, S1: &Subst) -> Subst {
+    let mut S: Subst = S1
+        .iter()
+        .map(|(alpha, tau)| (alpha.clone(), tau.clone().apply(S2)))
+        .collect();
+    for (alpha, tau) in S2 {
+        S.entry(alpha.clone()).or_insert_with(|| tau.clone());
     }
+    S
 }
 
 impl std::fmt::Display for Mono {
@@ -100,7 +389,7 @@ impl std::fmt::Display for Mono {
             Mono::App(C, taus) if C == ARROW => {
                 let [tau1, tau2] = &taus[..] else { panic!() };
 
-                if tau1.is_arrow() {
+                if tau1.extends_right() {
                     write!(f, "({})", tau1)?
                 } else {
                     write!(f, "{}", tau1)?
@@ -111,7 +400,7 @@ impl std::fmt::Display for Mono {
             Mono::App(C, taus) => {
                 write!(f, "{}", C)?;
                 for tau in taus {
-                    if tau.is_arrow() {
+                    if tau.extends_right() {
                         write!(f, " ({})", tau)?
                     } else {
                         write!(f, " {}", tau)?
@@ -119,6 +408,7 @@ impl std::fmt::Display for Mono {
                 }
                 Ok(())
             }
+            Mono::Rec(alpha, tau) => write!(f, "{} {} . {}", MU, alpha, tau),
         }
     }
 }
@@ -130,7 +420,7 @@ impl std::fmt::Display for Poly {
             tau.fmt(f)
         } else {
             write!(f, "∀")?;
-            for alpha in alphas {
+            for alpha in self.bound_vars() {
                 write!(f, " {}", alpha)?;
             }
             write!(f, " . {}", tau)
@@ -148,6 +438,8 @@ mod poly_eq {
                 sigma2: other,
                 left_to_right: HashMap::new(),
                 right_to_left: HashMap::new(),
+                recursive1: HashSet::new(),
+                recursive2: HashSet::new(),
             }
             .eq()
         }
@@ -158,6 +450,9 @@ mod poly_eq {
         sigma2: &'a Poly,
         left_to_right: HashMap<&'a String, &'a String>,
         right_to_left: HashMap<&'a String, &'a String>,
+        /// The variables bound by `μ` in sigma1 and sigma2, respectively, which are matched up like bound variables.
+        recursive1: HashSet<&'a String>,
+        recursive2: HashSet<&'a String>,
     }
 
     impl<'a> PolyEq<'a> {
@@ -171,10 +466,12 @@ mod poly_eq {
         fn structurally_equal(&mut self, tau1: &'a Mono, tau2: &'a Mono) -> bool {
             let Poly(alphas1, ..) = self.sigma1;
             let Poly(alphas2, ..) = self.sigma2;
+            let is_bound1 = |alpha: &String| alphas1.contains(alpha) || self.recursive1.contains(alpha);
+            let is_bound2 = |alpha: &String| alphas2.contains(alpha) || self.recursive2.contains(alpha);
 
             match (tau1, tau2) {
                 // alpha1 and alpha2 are bound variables of sigma1 and sigma2, respectively
-                (Mono::Var(alpha1), Mono::Var(alpha2)) if alphas1.contains(alpha1) && alphas2.contains(alpha2) => {
+                (Mono::Var(alpha1), Mono::Var(alpha2)) if is_bound1(alpha1) && is_bound2(alpha2) => {
                     match (self.left_to_right.get(alpha1), self.right_to_left.get(alpha2)) {
                         (None, None) => {
                             self.left_to_right.insert(alpha1, alpha2);
@@ -186,16 +483,115 @@ mod poly_eq {
                     }
                 }
                 // alpha1 and alpha2 are not bound in sigma1 and sigma2, respectively
-                (Mono::Var(alpha1), Mono::Var(alpha2)) if !alphas1.contains(alpha1) && !alphas2.contains(alpha2) => {
-                    alpha1 == alpha2
-                }
+                (Mono::Var(alpha1), Mono::Var(alpha2)) if !is_bound1(alpha1) && !is_bound2(alpha2) => alpha1 == alpha2,
                 // both sides are an application of equal structure
                 (Mono::App(C1, taus1), Mono::App(C2, taus2)) if C1 == C2 && taus1.len() == taus2.len() => taus1
-                    .into_iter()
-                    .zip(taus2.into_iter())
+                    .iter()
+                    .zip(taus2)
                     .all(|(tau1, tau2)| self.structurally_equal(tau1, tau2)),
+                // both sides are recursive types, whose variables are bound like those of sigma1 and sigma2
+                (Mono::Rec(alpha1, tau1), Mono::Rec(alpha2, tau2)) => {
+                    self.recursive1.insert(alpha1);
+                    self.recursive2.insert(alpha2);
+                    self.left_to_right.insert(alpha1, alpha2);
+                    self.right_to_left.insert(alpha2, alpha1);
+                    self.structurally_equal(tau1, tau2)
+                }
                 _ => false,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_poly;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let sigma = parse_poly("∀ x y . (y → x) → y → x").unwrap().normalize();
+
+        assert_eq!(sigma.to_string(), "∀ a b . (a → b) → a → b");
+    }
+
+    #[test]
+    fn test_normalize_avoids_free_variables() {
+        let sigma = parse_poly("∀ x y . a → x → y → b").unwrap().normalize();
+
+        assert_eq!(sigma.to_string(), "∀ c d . a → c → d → b");
+    }
+
+    #[test]
+    fn test_normalize_avoids_capture_by_recursive_types() {
+        // The bound variable `x` is renamed to `a`, which must not be captured by the `μ a` inside the type.
+        let sigma = parse_poly("∀ x . (μ a . a → x) → x").unwrap().normalize();
+
+        assert_eq!(sigma.to_string(), "∀ a . (μ b . b → a) → a");
+        assert_eq!(sigma, parse_poly("∀ x . (μ a . a → x) → x").unwrap());
+    }
+
+    #[test]
+    fn test_display_quantifiers_in_order_of_occurrence() {
+        let tau = Mono::arrow(Mono::Var("_3".into()), Mono::Var("_12".into()));
+        let sigma = Poly(
+            HashSet::from(["_12".into(), "_3".into(), "_7".into(), "_5".into()]),
+            tau,
+        );
+
+        assert_eq!(sigma.to_string(), "∀ _3 _12 _5 _7 . _3 → _12");
+    }
+
+    fn rec(alpha: &str, tau: Mono) -> Mono {
+        Mono::Rec(alpha.into(), Box::new(tau))
+    }
+
+    fn var(alpha: &str) -> Mono {
+        Mono::Var(alpha.into())
+    }
+
+    #[test]
+    fn test_canonicalize_cyclic_aliases() {
+        let aliases = HashMap::from([("_1".into(), Mono::arrow(var("_1"), var("_2")))]);
+
+        let tau = Mono::arrow(var("_1"), var("_2")).canonicalize(&aliases);
+
+        assert_eq!(
+            tau,
+            Mono::arrow(rec("_1", Mono::arrow(var("_1"), var("_2"))), var("_2"))
+        );
+        assert_eq!(tau.to_string(), "(μ _1 . _1 → _2) → _2");
+    }
+
+    #[test]
+    fn test_equivalent_up_to_unfolding() {
+        let tau = rec("t", Mono::arrow(var("t"), var("a")));
+        let unfolded = Mono::arrow(tau.clone(), var("a"));
+        let shifted = rec("s", Mono::arrow(Mono::arrow(var("s"), var("a")), var("a")));
+
+        assert!(tau.equivalent(&unfolded));
+        assert!(tau.equivalent(&shifted));
+        assert!(!tau.equivalent(&rec("t", Mono::arrow(var("t"), var("b")))));
+    }
+
+    #[test]
+    fn test_recursive_types_equal_up_to_renaming() {
+        let sigma1 = Poly(HashSet::from(["a".into()]), rec("t", Mono::arrow(var("t"), var("a"))));
+        let sigma2 = Poly(HashSet::from(["b".into()]), rec("s", Mono::arrow(var("s"), var("b"))));
+        let sigma3 = Poly(HashSet::from(["b".into()]), rec("s", Mono::arrow(var("b"), var("s"))));
+
+        assert_eq!(sigma1, sigma2);
+        assert_ne!(sigma1, sigma3);
+    }
+
+    #[test]
+    fn test_instances_of_recursive_types_up_to_renaming() {
+        let sigma = parse_poly("∀ a . μ t . t → a").unwrap();
+
+        assert!(sigma.is_more_general_than(&parse_poly("μ s . s → Int").unwrap()));
+        assert!(sigma.is_more_general_than(&parse_poly("μ a . a → Int").unwrap()));
+        // The variable bound by `μ` cannot be substituted for `a`.
+        assert!(!sigma.is_more_general_than(&parse_poly("μ s . s → s").unwrap()));
+    }
+}